@@ -0,0 +1,262 @@
+use crate::report::{json_object, json_option};
+use crate::simulation::Generation;
+use crate::sink::Sink;
+
+use gif::EncodingError;
+
+use std::io::{self, Write};
+use std::path::Path;
+
+// Largest block size for block entropies, so that every block fits in a table
+pub const MAX_BLOCK: usize = 16;
+
+// Measurements of one generation. Blocks and pairs of cells are counted around the
+// lattice, whatever its boundary
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationStats {
+    pub generation: u64,
+    // Fraction of occupied cells
+    pub density: f64,
+    // Number of maximal runs of occupied cells
+    pub runs: u64,
+    // Shannon entropy in bits of the blocks of 1, 2, ..., k cells
+    pub block_entropies: Vec<f64>,
+    // Correlation between cells 1, 2, ..., k apart, or None when every cell is equal
+    pub autocorrelation: Vec<Option<f64>>,
+}
+
+impl GenerationStats {
+    pub fn measure(generation: &Generation, k: usize) -> GenerationStats {
+        GenerationStats::from_cells(generation.index, &generation.cells.to_bit_vec(), k)
+    }
+
+    // cells holds one byte per cell, 1 for occupied cells
+    pub fn from_cells(generation: u64, cells: &[u8], k: usize) -> GenerationStats {
+        let len = cells.len();
+        let k = k.min(MAX_BLOCK);
+        let occupied = cells.iter().filter(|c| **c == 1).count();
+        let density = occupied as f64 / len as f64;
+        // A run starts at every occupied cell whose left neighbour is empty
+        let starts = (0..len)
+            .filter(|&i| cells[i] == 1 && cells[(i + len - 1) % len] == 0)
+            .count() as u64;
+        let runs = if occupied == len { 1 } else { starts };
+
+        let block_entropies = (1..=k)
+            .map(|size| {
+                let mut counts = vec![0usize; 1 << size];
+                for i in 0..len {
+                    let block = (0..size).fold(0, |b, j| (b << 1) | cells[(i + j) % len] as usize);
+                    counts[block] += 1;
+                }
+                -counts
+                    .iter()
+                    .filter(|c| **c > 0)
+                    .map(|c| {
+                        let p = *c as f64 / len as f64;
+                        p * p.log2()
+                    })
+                    .sum::<f64>()
+            })
+            .collect();
+
+        let variance = density * (1.0 - density);
+        let autocorrelation = (1..=k)
+            .map(|lag| {
+                if variance == 0.0 {
+                    return None;
+                }
+                let pairs = (0..len)
+                    .filter(|&i| cells[i] == 1 && cells[(i + lag) % len] == 1)
+                    .count();
+                Some((pairs as f64 / len as f64 - density * density) / variance)
+            })
+            .collect();
+
+        GenerationStats {
+            generation,
+            density,
+            runs,
+            block_entropies,
+            autocorrelation,
+        }
+    }
+
+    pub fn csv_header(k: usize) -> String {
+        let mut columns = vec![
+            String::from("generation"),
+            String::from("density"),
+            String::from("runs"),
+        ];
+        columns.extend((1..=k.min(MAX_BLOCK)).map(|size| format!("entropy_{}", size)));
+        columns.extend((1..=k.min(MAX_BLOCK)).map(|lag| format!("autocorrelation_{}", lag)));
+        columns.join(",")
+    }
+
+    // Undefined autocorrelations are left empty
+    pub fn to_csv(&self) -> String {
+        let mut fields = vec![
+            self.generation.to_string(),
+            self.density.to_string(),
+            self.runs.to_string(),
+        ];
+        fields.extend(self.block_entropies.iter().map(|h| h.to_string()));
+        fields.extend(
+            self.autocorrelation
+                .iter()
+                .map(|c| c.map(|c| c.to_string()).unwrap_or_default()),
+        );
+        fields.join(",")
+    }
+
+    // Single line of JSON
+    pub fn to_json(&self) -> String {
+        let entropies: Vec<String> = self.block_entropies.iter().map(|h| h.to_string()).collect();
+        let autocorrelation: Vec<String> = self
+            .autocorrelation
+            .iter()
+            .map(|c| json_option(*c))
+            .collect();
+        json_object(&[
+            ("generation", self.generation.to_string()),
+            ("density", self.density.to_string()),
+            ("runs", self.runs.to_string()),
+            ("block_entropies", format!("[{}]", entropies.join(","))),
+            (
+                "autocorrelation",
+                format!("[{}]", autocorrelation.join(",")),
+            ),
+        ])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatsFormat {
+    // Header line followed by one row per generation
+    Csv,
+    // One JSON object per line
+    Json,
+}
+
+impl StatsFormat {
+    // JSON for .json, .jsonl and .ndjson files, CSV otherwise
+    pub fn from_path(path: &str) -> StatsFormat {
+        let extension = Path::new(path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("json") | Some("jsonl") | Some("ndjson") => StatsFormat::Json,
+            _ => StatsFormat::Csv,
+        }
+    }
+}
+
+// Writes the statistics of each generation as it arrives, so that long runs can be
+// followed while they go
+pub struct StatsSink<W: Write> {
+    writer: W,
+    format: StatsFormat,
+    k: usize,
+    header_written: bool,
+    // First error met by recording, returned by finish
+    error: Option<io::Error>,
+}
+
+impl<W: Write> StatsSink<W> {
+    pub fn new(writer: W, format: StatsFormat, k: usize) -> StatsSink<W> {
+        StatsSink {
+            writer,
+            format,
+            k,
+            header_written: false,
+            error: None,
+        }
+    }
+
+    // Passes generations through, recording each one on the way, e.g. to write the
+    // statistics of a run while it is drawn
+    pub fn recording<'a, I: Iterator<Item = Generation> + 'a>(
+        &'a mut self,
+        generations: I,
+    ) -> impl Iterator<Item = Generation> + 'a {
+        generations.inspect(move |generation| {
+            if self.error.is_none() {
+                self.error = self.record(generation).err();
+            }
+        })
+    }
+
+    pub fn record(&mut self, generation: &Generation) -> io::Result<()> {
+        let stats = GenerationStats::measure(generation, self.k);
+        match self.format {
+            StatsFormat::Csv => {
+                if !self.header_written {
+                    writeln!(self.writer, "{}", GenerationStats::csv_header(self.k))?;
+                    self.header_written = true;
+                }
+                writeln!(self.writer, "{}", stats.to_csv())
+            }
+            StatsFormat::Json => writeln!(self.writer, "{}", stats.to_json()),
+        }
+    }
+}
+
+impl<W: Write> Sink for StatsSink<W> {
+    fn push(&mut self, generation: &Generation) -> Result<(), EncodingError> {
+        Ok(self.record(generation)?)
+    }
+
+    fn finish(&mut self) -> Result<(), EncodingError> {
+        if let Some(e) = self.error.take() {
+            return Err(e.into());
+        }
+        Ok(self.writer.flush()?)
+    }
+}
+
+// Columns drawn to the right of each generation in an animation: a gap, a bar for the
+// density and a bar for the entropy of blocks of k cells, relative to its maximum of k bits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatsStrip {
+    pub k: usize,
+}
+
+const STRIP_GAP: usize = 2;
+const STRIP_BAR: usize = 24;
+
+impl StatsStrip {
+    pub const WIDTH: u16 = (3 * STRIP_GAP + 2 * STRIP_BAR) as u16;
+    // Widest lattice a strip can be drawn next to in a frame
+    pub const MAX_CELLS: u16 = u16::MAX - StatsStrip::WIDTH;
+
+    // Width of frames of width cells, with a strip to their right if strip is set.
+    // Cells past MAX_CELLS make way for the strip
+    pub fn frame_width(width: u16, strip: bool) -> u16 {
+        if strip {
+            width.min(StatsStrip::MAX_CELLS) + StatsStrip::WIDTH
+        } else {
+            width
+        }
+    }
+
+    pub fn new(k: usize) -> StatsStrip {
+        StatsStrip {
+            k: k.clamp(1, MAX_BLOCK),
+        }
+    }
+
+    // One byte per pixel, 1 for the bars, as in the rows of a frame
+    pub fn draw(&self, generation: &Generation) -> Vec<u8> {
+        let stats = GenerationStats::measure(generation, self.k);
+        let entropy = stats.block_entropies[self.k - 1] / self.k as f64;
+        let mut pixels = Vec::with_capacity(StatsStrip::WIDTH.into());
+        for value in &[stats.density, entropy] {
+            let filled = (value.clamp(0.0, 1.0) * STRIP_BAR as f64).round() as usize;
+            pixels.resize(pixels.len() + STRIP_GAP, 0);
+            pixels.resize(pixels.len() + filled, 1);
+            pixels.resize(pixels.len() + STRIP_BAR - filled, 0);
+        }
+        pixels.resize(pixels.len() + STRIP_GAP, 0);
+        pixels
+    }
+}
@@ -0,0 +1,228 @@
+use crate::cancel;
+use crate::image_manip::bitmap::BitMap;
+use crate::image_manip::{gen_next_image, init_image};
+use crate::prog::Message;
+
+use gif::EncodingError;
+use gif::{Encoder, Frame, Repeat};
+
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::sync::mpsc::Sender;
+
+// Pixels between neighbouring tiles and around the edge of the atlas
+const GAP: usize = 2;
+
+// Palette indices, see COLOR_MAP. White and black match the pixels of init_image
+const BLACK: u8 = 1;
+const GRID: u8 = 2;
+const LABEL: u8 = 3;
+const COLOR_MAP: [u8; 12] = [
+    0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x80, 0x80, 0x80, 0xE0, 0xE0, 0xE0,
+];
+
+// 3x5 pixel glyphs for the digits 0-9, one row per entry, most significant bit leftmost
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+// Grid of rules evolved from the same initial bitmap, one labeled tile per rule
+pub struct Atlas {
+    pub rules: Vec<u8>,
+    pub columns: usize,
+}
+
+struct Layout {
+    tile_width: usize,
+    tile_height: usize,
+    label_height: usize,
+    scale: usize,
+    columns: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Atlas {
+    fn layout(&self, width: u16, height: u16) -> Layout {
+        let tile_width: usize = width.into();
+        // Make labels as large as possible while "255" still fits in a tile
+        let scale = (tile_width / 12).clamp(1, 4);
+        let label_height = 7 * scale;
+        let tile_height = label_height + height as usize;
+        let columns = self.columns.clamp(1, self.rules.len().max(1));
+        let rows = self.rules.chunks(columns).len();
+        Layout {
+            tile_width,
+            tile_height,
+            label_height,
+            scale,
+            columns,
+            width: columns * (tile_width + GAP) + GAP,
+            height: rows * (tile_height + GAP) + GAP,
+        }
+    }
+
+    // Width and height in pixels of the atlas built from tiles of the given size
+    pub fn dimensions(&self, width: u16, height: u16) -> (usize, usize) {
+        let layout = self.layout(width, height);
+        (layout.width, layout.height)
+    }
+}
+
+fn draw_label(
+    canvas: &mut [u8],
+    canvas_width: usize,
+    x0: usize,
+    y0: usize,
+    layout: &Layout,
+    rule: u8,
+) {
+    for y in 0..layout.label_height {
+        let start = (y0 + y) * canvas_width + x0;
+        canvas[start..start + layout.tile_width].fill(LABEL);
+    }
+    let scale = layout.scale;
+    for (d, digit) in rule.to_string().bytes().enumerate() {
+        let glyph = &DIGITS[(digit - b'0') as usize];
+        for (gy, row) in glyph.iter().enumerate() {
+            for gx in 0..3 {
+                if row & (0b100 >> gx) == 0 {
+                    continue;
+                }
+                for sy in 0..scale {
+                    for sx in 0..scale {
+                        let x = scale + (d * 4 + gx) * scale + sx;
+                        let y = scale + gy * scale + sy;
+                        // Clip labels that do not fit in narrow tiles
+                        if x < layout.tile_width {
+                            canvas[(y0 + y) * canvas_width + x0 + x] = BLACK;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Top left corner of the spacetime diagram in tile i
+fn tile_origin(layout: &Layout, i: usize) -> (usize, usize) {
+    let column = i % layout.columns;
+    let row = i / layout.columns;
+    (
+        GAP + column * (layout.tile_width + GAP),
+        GAP + row * (layout.tile_height + GAP) + layout.label_height,
+    )
+}
+
+fn blit_tile(canvas: &mut [u8], layout: &Layout, i: usize, img: &[u8]) {
+    let (x0, y0) = tile_origin(layout, i);
+    for (y, row) in img.chunks(layout.tile_width).enumerate() {
+        let start = (y0 + y) * layout.width + x0;
+        canvas[start..start + layout.tile_width].copy_from_slice(row);
+    }
+}
+
+// Builds the atlas as a PNG if file_name ends in .png, otherwise as a GIF animated
+// over steps frames like the output of build_gif. A PNG counts as a single frame for
+// the progress bar
+pub fn build_atlas(
+    atlas: &Atlas,
+    width: u16,
+    height: u16,
+    steps: u32,
+    init_line: &BitMap,
+    file_name: &str,
+    progress_bar_tx_wrap: Option<&Sender<Message>>,
+) -> Result<(), EncodingError> {
+    let layout = atlas.layout(width, height);
+    let is_png = file_name.to_lowercase().ends_with(".png");
+    if !is_png && (layout.width > u16::MAX as usize || layout.height > u16::MAX as usize) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Atlas of {}x{} pixels is too large for a GIF, use fewer columns or a .png output",
+                layout.width, layout.height
+            ),
+        )
+        .into());
+    }
+
+    let mut canvas = vec![GRID; layout.width * layout.height];
+    let mut lines = Vec::with_capacity(atlas.rules.len());
+    let mut images = Vec::with_capacity(atlas.rules.len());
+    for (i, rule) in atlas.rules.iter().enumerate() {
+        let (x0, y0) = tile_origin(&layout, i);
+        draw_label(
+            &mut canvas,
+            layout.width,
+            x0,
+            y0 - layout.label_height,
+            &layout,
+            *rule,
+        );
+        let mut line = init_line.clone();
+        let img = init_image(width, height, &mut line, *rule)?;
+        blit_tile(&mut canvas, &layout, i, &img);
+        lines.push(line);
+        images.push(img);
+    }
+
+    let file = BufWriter::new(File::create(file_name)?);
+    if is_png {
+        let mut encoder = png::Encoder::new(file, layout.width as u32, layout.height as u32);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(COLOR_MAP.to_vec());
+        let mut writer = encoder.write_header().map_err(io::Error::from)?;
+        writer.write_image_data(&canvas).map_err(io::Error::from)?;
+        if let Some(progress_bar_tx) = progress_bar_tx_wrap {
+            progress_bar_tx.send(Message::Update(0)).unwrap();
+            progress_bar_tx.send(Message::Kill).unwrap();
+        }
+        return Ok(());
+    }
+
+    let (canvas_width, canvas_height) = (layout.width as u16, layout.height as u16);
+    let mut encoder = Encoder::new(file, canvas_width, canvas_height, &COLOR_MAP)?;
+    encoder.set_repeat(Repeat::Infinite)?;
+    encoder.write_frame(&Frame::from_indexed_pixels(
+        canvas_width,
+        canvas_height,
+        &canvas,
+        None,
+    ))?;
+
+    for s in 1..steps {
+        if cancel::is_cancelled() {
+            break;
+        }
+        for (i, rule) in atlas.rules.iter().enumerate() {
+            gen_next_image(&mut images[i], width, height, &mut lines[i], *rule)?;
+            blit_tile(&mut canvas, &layout, i, &images[i]);
+        }
+        encoder.write_frame(&Frame::from_indexed_pixels(
+            canvas_width,
+            canvas_height,
+            &canvas,
+            None,
+        ))?;
+        if let Some(progress_bar_tx) = progress_bar_tx_wrap {
+            progress_bar_tx.send(Message::Update(s)).unwrap();
+        }
+    }
+    if let Some(progress_bar_tx) = progress_bar_tx_wrap {
+        progress_bar_tx.send(Message::Kill).unwrap();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Set once the user asks to stop, e.g. with Ctrl-C. Long running loops check it
+// between generations and wind down, leaving valid output behind
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+pub fn cancel() {
+    CANCELLED.store(true, Ordering::SeqCst);
+}
+
+// Makes SIGINT and SIGTERM cancel the run instead of killing the process. A second
+// signal kills it as usual
+#[cfg(unix)]
+pub fn install_handler() {
+    extern "C" fn handle(signal: libc::c_int) {
+        // Only async-signal-safe work here: a store and resetting the disposition
+        CANCELLED.store(true, Ordering::SeqCst);
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+        }
+    }
+    let handler = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install_handler() {}
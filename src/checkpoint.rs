@@ -0,0 +1,259 @@
+use crate::image_manip::bitmap::BitMap;
+use crate::image_manip::metadata::Metadata;
+use crate::prog::Message;
+use crate::report::Stats;
+use crate::simulation::{Generation, Simulation};
+use crate::sink::{drive, GifSink, Sink, Window};
+
+use gif::EncodingError;
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+// State of a long run after some of its frames were written, enough to carry on from
+// there. Stored as the run's metadata followed by more key=value lines. Only the initial
+// bitmap is random, so the metadata's seed covers the random state of the run
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub metadata: Metadata,
+    // Output being built and the file holding it until it is complete
+    pub output: String,
+    pub partial: String,
+    // Number of frames between checkpoints
+    pub every: u32,
+    pub frames_done: u32,
+    // Length of the partial file up to the end of the last frame done
+    pub bytes: u64,
+    // Generation the next frame starts at, and its cells as 1s and 0s
+    pub generation: u64,
+    pub cells: String,
+}
+
+impl Checkpoint {
+    // Checkpoint of a run that has not written any frames yet
+    pub fn new(metadata: &Metadata, output: &str, partial: &str, every: u32) -> Checkpoint {
+        Checkpoint {
+            metadata: metadata.clone(),
+            output: String::from(output),
+            partial: String::from(partial),
+            every: every.max(1),
+            frames_done: 0,
+            bytes: 0,
+            generation: 0,
+            cells: metadata.bitmap.clone(),
+        }
+    }
+
+    // Where checkpoints of the output are written
+    pub fn path(output: &str) -> String {
+        format!("{}.checkpoint", output)
+    }
+
+    pub fn encode(&self) -> String {
+        let lines = [
+            self.metadata.encode(),
+            format!("output={}", self.output),
+            format!("partial={}", self.partial),
+            format!("every={}", self.every),
+            format!("frames_done={}", self.frames_done),
+            format!("bytes={}", self.bytes),
+            format!("generation={}", self.generation),
+            format!("cells={}", self.cells),
+        ];
+        lines.join("\n")
+    }
+
+    pub fn decode(text: &str) -> Result<Checkpoint, String> {
+        let mut checkpoint = Checkpoint::new(&Metadata::decode(text)?, "", "", 1);
+        let mut seen = Vec::new();
+        for (key, value) in text.lines().filter_map(|l| l.split_once('=')) {
+            let invalid = || format!("Invalid value '{}' for {}", value, key);
+            match key {
+                "output" => checkpoint.output = value.to_string(),
+                "partial" => checkpoint.partial = value.to_string(),
+                "every" => {
+                    checkpoint.every = value
+                        .parse()
+                        .ok()
+                        .filter(|every| *every > 0)
+                        .ok_or_else(invalid)?
+                }
+                "frames_done" => checkpoint.frames_done = value.parse().map_err(|_| invalid())?,
+                "bytes" => checkpoint.bytes = value.parse().map_err(|_| invalid())?,
+                "generation" => checkpoint.generation = value.parse().map_err(|_| invalid())?,
+                "cells" => checkpoint.cells = value.to_string(),
+                _ => continue,
+            }
+            seen.push(key);
+        }
+        for key in &[
+            "output",
+            "partial",
+            "frames_done",
+            "bytes",
+            "generation",
+            "cells",
+        ] {
+            if !seen.contains(key) {
+                return Err(format!("Checkpoint is missing {}", key));
+            }
+        }
+        Ok(checkpoint)
+    }
+
+    pub fn read(path: &str) -> Result<Checkpoint, String> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        Checkpoint::decode(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    // Replaces the file at path in one step, so that a crash leaves the previous checkpoint
+    pub fn write(&self, path: &str) -> io::Result<()> {
+        let temp = format!("{}.tmp", path);
+        let mut file = fs::File::create(&temp)?;
+        file.write_all((self.encode() + "\n").as_bytes())?;
+        file.sync_all()?;
+        fs::rename(temp, path)
+    }
+
+    // File holding the frames done so far, either the partial file of a run that
+    // stopped or the output of one that was interrupted and moved into place
+    pub fn find_partial(&self) -> Option<&str> {
+        [self.partial.as_str(), self.output.as_str()]
+            .iter()
+            .copied()
+            .find(|path| {
+                fs::metadata(path)
+                    .map(|m| m.len() >= self.bytes)
+                    .unwrap_or(false)
+            })
+    }
+
+    // Simulation from the start of the next frame on
+    pub fn simulation(&self) -> Simulation {
+        Simulation::new(
+            self.metadata.rule.into(),
+            self.metadata.boundary,
+            BitMap::from_bit_string(&self.cells),
+        )
+        .starting_at(self.generation)
+    }
+
+    // Number of generations left to draw
+    pub fn generations_left(&self) -> u64 {
+        let total = Window::<GifSink<io::Sink>>::generations_for(
+            self.metadata.height,
+            self.metadata.frames,
+        );
+        (self.metadata.start + total).saturating_sub(self.generation)
+    }
+
+    // Metadata of a new animation holding the frames that are left, starting from the
+    // cells of the next frame
+    pub fn segment_metadata(&self) -> Metadata {
+        let mut metadata = Metadata::new(
+            self.metadata.rule,
+            self.metadata.width,
+            self.metadata.height,
+            self.metadata.frames - self.frames_done,
+            &BitMap::from_bit_string(&self.cells),
+        );
+        metadata.boundary = self.metadata.boundary;
+        metadata.strip = self.metadata.strip;
+        metadata
+    }
+}
+
+// Passes generations on to a sink of animation frames and writes a checkpoint every
+// checkpoint.every frames. bytes is the length of the output so far, which the
+// output's writer adds to
+pub struct Checkpointer<S: Sink> {
+    sink: S,
+    checkpoint: Checkpoint,
+    path: String,
+    bytes: Arc<AtomicU64>,
+    // Generations of the frame being drawn, oldest first
+    window: VecDeque<BitMap>,
+}
+
+impl<S: Sink> Checkpointer<S> {
+    pub fn new(sink: S, checkpoint: Checkpoint, bytes: Arc<AtomicU64>) -> Checkpointer<S> {
+        Checkpointer {
+            sink,
+            path: Checkpoint::path(&checkpoint.output),
+            checkpoint,
+            bytes,
+            window: VecDeque::new(),
+        }
+    }
+}
+
+impl<S: Sink> Sink for Checkpointer<S> {
+    fn push(&mut self, generation: &Generation) -> Result<(), EncodingError> {
+        self.sink.push(generation)?;
+        let height = self.checkpoint.metadata.height as usize;
+        self.window.push_back(generation.cells.clone());
+        if self.window.len() < height {
+            return Ok(());
+        }
+        // A frame was just written
+        self.window.pop_front();
+        let next = generation.index + 2 - height as u64;
+        // Frames are counted from the generation the run starts at
+        let frames_done = next.saturating_sub(self.checkpoint.metadata.start) as u32;
+        let due = self
+            .checkpoint
+            .frames_done
+            .saturating_add(self.checkpoint.every);
+        if frames_done < due {
+            return Ok(());
+        }
+        let cells = match self.window.front() {
+            Some(cells) => cells.to_bit_string(),
+            // Frames one generation high start at the generation after this one
+            None => {
+                let mut cells = generation.cells.clone();
+                let metadata = &self.checkpoint.metadata;
+                cells.rule_step_with_boundary(metadata.rule, metadata.boundary);
+                cells.to_bit_string()
+            }
+        };
+        self.checkpoint.frames_done = frames_done;
+        self.checkpoint.generation = next;
+        self.checkpoint.cells = cells;
+        self.checkpoint.bytes = self.bytes.load(Ordering::Relaxed);
+        self.checkpoint.write(&self.path)?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), EncodingError> {
+        self.sink.finish()
+    }
+}
+
+// Draws the generations into sink as frames of an animation, writing checkpoints as
+// it goes
+pub fn render_checkpointed<I: Iterator<Item = Generation>, S: Sink>(
+    generations: I,
+    sink: S,
+    checkpoint: Checkpoint,
+    bytes: Arc<AtomicU64>,
+    progress_bar_tx_wrap: Option<&Sender<Message>>,
+) -> Result<Stats, EncodingError> {
+    let mut checkpointer = Checkpointer::new(sink, checkpoint, bytes);
+    drive(generations, &mut checkpointer, progress_bar_tx_wrap)
+}
+
+// Removes the checkpoint of a finished output, if there is one
+pub fn remove(output: &str) -> io::Result<()> {
+    let path = Checkpoint::path(output);
+    if Path::new(&path).exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,321 @@
+use crate::damage::Damage;
+use crate::image_manip::bitmap::{BitMap, Boundary};
+use crate::report::{json_object, json_option, CycleDetector};
+use crate::simulation::Simulation;
+
+use std::collections::HashMap;
+use std::fmt;
+
+// Wolfram's four classes of behaviour from random initial states
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WolframClass {
+    // Every cell ends up in the same state
+    Uniform,
+    // Stable or periodic structures that keep damage local
+    Periodic,
+    // Aperiodic patterns that spread damage everywhere
+    Chaotic,
+    // Localised structures that move and interact on a regular background
+    Complex,
+}
+
+impl WolframClass {
+    pub fn numeral(&self) -> &'static str {
+        match self {
+            WolframClass::Uniform => "I",
+            WolframClass::Periodic => "II",
+            WolframClass::Chaotic => "III",
+            WolframClass::Complex => "IV",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WolframClass::Uniform => "uniform",
+            WolframClass::Periodic => "periodic",
+            WolframClass::Chaotic => "chaotic",
+            WolframClass::Complex => "complex",
+        }
+    }
+}
+
+impl fmt::Display for WolframClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.numeral())
+    }
+}
+
+// Runs whose damage covers less than this fraction of the lattice keep it local
+const LOCAL_DAMAGE: f64 = 0.05;
+// Standard deviation of the density below which it has settled
+const SETTLED_DENSITY: f64 = 0.05;
+// Complexity below which a diagram is too structured to be chaotic
+const CHAOTIC_COMPLEXITY: f64 = 0.5;
+// Regularity above which most of a diagram is a periodic background
+const REGULAR_BACKGROUND: f64 = 0.5;
+// Largest number of generations looked back for the background's period
+const MAX_LAG: usize = 8;
+
+// Measurements of one run, or averages over several
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evidence {
+    // Fraction of runs that ended with every cell equal
+    pub uniform: f64,
+    // Fraction of runs that entered a cycle, and the longest transient and period seen
+    pub cycled: f64,
+    pub transient: Option<u64>,
+    pub period: Option<u64>,
+    // Density of the last generation, and its standard deviation over the second half
+    // of the run, which is near 0 once the density has converged
+    pub density: f64,
+    pub density_fluctuation: f64,
+    // Fraction of cells differing from a copy with the middle cell flipped, over the last
+    // quarter of the run
+    pub damage: f64,
+    // Lempel-Ziv complexity of the second half of the spacetime diagram relative to
+    // that of noise, near 1 for noise and near 0 for regular patterns
+    pub complexity: f64,
+    // How much more often than chance cells of the last quarter equal the cell a fixed
+    // number of generations and cells away, for the best such displacement. Near 1 when
+    // the diagram is mostly a periodic background, near 0 for noise
+    pub regularity: f64,
+}
+
+impl Evidence {
+    // Class this evidence points to, following Wolfram's descriptions in turn
+    pub fn class(&self) -> WolframClass {
+        if self.uniform >= 0.5 {
+            WolframClass::Uniform
+        } else if self.cycled >= 0.5
+            || (self.damage < LOCAL_DAMAGE && self.density_fluctuation < SETTLED_DENSITY)
+        {
+            WolframClass::Periodic
+        } else if self.complexity < CHAOTIC_COMPLEXITY || self.regularity >= REGULAR_BACKGROUND {
+            WolframClass::Complex
+        } else {
+            WolframClass::Chaotic
+        }
+    }
+
+    fn mean(runs: &[Evidence]) -> Evidence {
+        let n = runs.len().max(1) as f64;
+        let mean = |f: fn(&Evidence) -> f64| runs.iter().map(f).sum::<f64>() / n;
+        Evidence {
+            uniform: mean(|e| e.uniform),
+            cycled: mean(|e| e.cycled),
+            transient: runs.iter().filter_map(|e| e.transient).max(),
+            period: runs.iter().filter_map(|e| e.period).max(),
+            density: mean(|e| e.density),
+            density_fluctuation: mean(|e| e.density_fluctuation),
+            damage: mean(|e| e.damage),
+            complexity: mean(|e| e.complexity),
+            regularity: mean(|e| e.regularity),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Classification {
+    pub rule: u8,
+    pub class: WolframClass,
+    // Fraction of runs classified the same way
+    pub confidence: f64,
+    // Averaged over every run
+    pub evidence: Evidence,
+}
+
+impl Classification {
+    // Single line of JSON
+    pub fn to_json(&self) -> String {
+        let e = &self.evidence;
+        json_object(&[
+            ("rule", self.rule.to_string()),
+            ("class", format!("\"{}\"", self.class.numeral())),
+            ("confidence", self.confidence.to_string()),
+            ("uniform", e.uniform.to_string()),
+            ("cycled", e.cycled.to_string()),
+            ("transient", json_option(e.transient)),
+            ("period", json_option(e.period)),
+            ("density", e.density.to_string()),
+            ("density_fluctuation", e.density_fluctuation.to_string()),
+            ("damage", e.damage.to_string()),
+            ("complexity", e.complexity.to_string()),
+            ("regularity", e.regularity.to_string()),
+        ])
+    }
+}
+
+// Guesses the Wolfram class of rules from runs of random initial states on a periodic
+// lattice, one per seed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Classifier {
+    pub width: u64,
+    pub generations: u64,
+    pub density: f64,
+    pub seeds: Vec<u64>,
+}
+
+impl Default for Classifier {
+    // Odd widths keep additive rules such as 90 from dying out on lattices of 2^k cells
+    fn default() -> Classifier {
+        Classifier {
+            width: 149,
+            generations: 400,
+            density: 0.5,
+            seeds: (1..=8).collect(),
+        }
+    }
+}
+
+impl Classifier {
+    pub fn classify(&self, rule: u8) -> Classification {
+        let noise = self.noise_complexity();
+        let runs: Vec<Evidence> = self
+            .seeds
+            .iter()
+            .map(|s| self.run(rule, *s, noise))
+            .collect();
+        let classes: Vec<WolframClass> = runs.iter().map(Evidence::class).collect();
+        let mut votes: HashMap<WolframClass, usize> = HashMap::new();
+        for class in &classes {
+            *votes.entry(*class).or_insert(0) += 1;
+        }
+        // Ties go to the lower class
+        let (class, count) = votes
+            .into_iter()
+            .max_by_key(|(class, count)| (*count, std::cmp::Reverse(*class)))
+            .unwrap_or((WolframClass::Uniform, 0));
+        Classification {
+            rule,
+            class,
+            confidence: count as f64 / classes.len().max(1) as f64,
+            evidence: Evidence::mean(&runs),
+        }
+    }
+
+    // Complexity of fair coin flips the size of the diagrams measured, which the
+    // complexity of each run is relative to
+    pub fn noise_complexity(&self) -> f64 {
+        let cells = (self.generations - self.generations / 2) * self.width;
+        lz_complexity(&BitMap::random_seeded(cells, 0.5, 0).to_bit_vec())
+    }
+
+    // Evidence from a single run, given the complexity of noise
+    pub fn run(&self, rule: u8, seed: u64, noise: f64) -> Evidence {
+        let cells = BitMap::random_seeded(self.width, self.density, seed);
+        let simulation = Simulation::new(rule.into(), Boundary::Periodic, cells);
+        let middle = self.width as usize / 2;
+        let damage = Damage::new(simulation, middle).unwrap();
+
+        let mut cycles = CycleDetector::new();
+        let mut densities = Vec::new();
+        let mut diagram: Vec<Vec<u8>> = Vec::new();
+        let mut hamming = Vec::new();
+        let mut last = Vec::new();
+        for generation in damage.take(self.generations as usize) {
+            let row = generation.original.to_bit_vec();
+            cycles.observe(generation.index, &row);
+            if generation.index >= self.generations / 2 {
+                densities.push(row.iter().filter(|c| **c == 1).count() as f64 / row.len() as f64);
+                diagram.push(row.clone());
+            }
+            if generation.index >= self.generations * 3 / 4 {
+                hamming.push(generation.hamming as f64 / row.len() as f64);
+            }
+            last = row;
+        }
+
+        let mean = |v: &[f64]| v.iter().sum::<f64>() / v.len().max(1) as f64;
+        let density = last.iter().filter(|c| **c == 1).count() as f64 / last.len().max(1) as f64;
+        let average = mean(&densities);
+        let variance = mean(
+            &densities
+                .iter()
+                .map(|d| (d - average).powi(2))
+                .collect::<Vec<_>>(),
+        );
+        let cycle = cycles.cycle();
+        Evidence {
+            uniform: if density == 0.0 || density == 1.0 {
+                1.0
+            } else {
+                0.0
+            },
+            cycled: if cycle.is_some() { 1.0 } else { 0.0 },
+            transient: cycle.map(|c| c.start),
+            period: cycle.map(|c| c.period),
+            density,
+            density_fluctuation: variance.sqrt(),
+            damage: mean(&hamming),
+            complexity: lz_complexity(&diagram.concat()) / noise,
+            regularity: regularity(&diagram[diagram.len() / 2..]),
+        }
+    }
+}
+
+// Best agreement between the rows of a diagram and the rows up to MAX_LAG generations
+// earlier shifted by at most as many cells, relative to the agreement expected by chance
+// at the diagram's density
+pub fn regularity(rows: &[Vec<u8>]) -> f64 {
+    let width = rows.first().map_or(0, |r| r.len());
+    let cells = (rows.len() * width) as f64;
+    let density = rows.iter().flatten().filter(|c| **c == 1).count() as f64 / cells;
+    let chance = density * density + (1.0 - density) * (1.0 - density);
+    if rows.len() <= MAX_LAG || chance >= 1.0 {
+        return 1.0;
+    }
+    let compared = ((rows.len() - MAX_LAG) * width) as f64;
+    let mut best: f64 = 0.0;
+    for lag in 1..=MAX_LAG {
+        for shift in 0..width {
+            // Shifts wrap around the lattice, so only those of at most lag cells count
+            if shift > lag && width - shift > lag {
+                continue;
+            }
+            let agree: usize = (MAX_LAG..rows.len())
+                .map(|t| {
+                    let (row, earlier) = (&rows[t], &rows[t - lag]);
+                    (0..width)
+                        .filter(|&x| row[x] == earlier[(x + shift) % width])
+                        .count()
+                })
+                .sum();
+            // Nothing beats a background that fills the whole diagram
+            if agree as f64 == compared {
+                return 1.0;
+            }
+            best = best.max(agree as f64 / compared);
+        }
+    }
+    ((best - chance) / (1.0 - chance)).max(0.0)
+}
+
+// Number of phrases in the LZ78 parsing of bits, scaled by log2 of itself over the length
+// so that long runs of fair coin flips come out near 1
+pub fn lz_complexity(bits: &[u8]) -> f64 {
+    if bits.len() < 2 {
+        return 0.0;
+    }
+    // Each phrase is a known phrase followed by one more bit. Phrases are numbered from 1,
+    // with 0 for the empty phrase at the root and for phrases not seen yet
+    let mut phrases: Vec<[usize; 2]> = vec![[0, 0]];
+    let mut node = 0;
+    let mut count = 0;
+    for bit in bits {
+        let next = &mut phrases[node][*bit as usize];
+        if *next == 0 {
+            count += 1;
+            *next = count;
+            phrases.push([0, 0]);
+            node = 0;
+        } else {
+            node = *next;
+        }
+    }
+    // A trailing partial phrase counts as one more
+    if node != 0 {
+        count += 1;
+    }
+    let count = count as f64;
+    count * count.log2() / bits.len() as f64
+}
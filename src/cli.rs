@@ -1,11 +1,58 @@
-use crate::image_manip::bitmap::BitMap;
-use clap::{App, Arg};
+use crate::analysis::{StatsStrip, MAX_BLOCK};
+use crate::atlas::Atlas;
+use crate::classify::Classifier;
+use crate::damage::Derrida;
+use crate::image_manip::bitmap::{BitMap, Boundary};
+use crate::image_manip::import::ImageRow;
+use crate::image_manip::init::Init;
+use crate::image_manip::is_still;
+use crate::image_manip::metadata::Metadata;
+use crate::image_manip::parse::parse_bitmap;
+use crate::lattice::{check_quiescent, Lattice};
+use crate::prog::Verbosity;
+use crate::report::Reporting;
+use crate::rule::Rule;
+use crate::sweep::{parse_float_list, parse_int_list, Sweep};
+use crate::viewport::Track;
+use clap::{App, AppSettings, Arg, SubCommand};
 use std::convert::*;
 use std::fs::File;
 use std::io::Read;
+use std::path::Path;
 use std::process::*;
+use std::thread;
 
 pub const FAILURE_CODE: i32 = 1;
+// Output name that streams the GIF to stdout
+pub const STDOUT: &str = "-";
+const DEFAULT_BLOCKS: usize = 4;
+
+pub enum Command {
+    Render(Box<CLIArgs>),
+    Equivalents(Rule),
+    Info(Rule),
+    // Metadata of a GIF, optionally rebuilt to the given output
+    GifInfo {
+        path: String,
+        rerun: Option<String>,
+        force: bool,
+    },
+    // Guess the Wolfram class of each rule, printing the evidence as text or JSON
+    Classify {
+        rules: Vec<u8>,
+        classifier: Classifier,
+        json: bool,
+    },
+    // Carry on a checkpointed run, or write the rest of it to segment
+    Resume {
+        checkpoint: String,
+        segment: Option<String>,
+        force: bool,
+        disable_prog: bool,
+        verbosity: Verbosity,
+    },
+}
+
 pub struct CLIArgs {
     pub width: u16,
     pub height: u16,
@@ -16,9 +63,45 @@ pub struct CLIArgs {
     pub density: f64,
     pub bitmap: Option<BitMap>,
     pub disable_prog: bool,
+    pub verbosity: Verbosity,
+    pub reporting: Reporting,
+    pub boundary: Boundary,
+    // Whether existing outputs may be overwritten
+    pub force: bool,
+    pub seed: Option<u64>,
+    pub sweep: Option<Sweep>,
+    pub atlas: Option<Atlas>,
+    pub init: Option<Init>,
+    // Whether the initial bitmap is the last row of an earlier animation
+    pub continued: bool,
+    // Generation of the first frame
+    pub start_generation: u64,
+    // Number of generations between consecutive rows
+    pub every: u64,
+    pub lattice: Lattice,
+    // Whether images of unbounded lattices widen to every cell the run reaches
+    pub expand_canvas: bool,
+    // Number of cells simulated, if more than the width drawn
+    pub lattice_width: Option<u64>,
+    // Leftmost cell drawn at the first frame, and how the window moves from there
+    pub window_start: Option<i64>,
+    pub track: Track,
+    // Number of frames between checkpoints, if any are written
+    pub checkpoint: Option<u32>,
+    // File the statistics of each generation are written to, or - for stdout
+    pub stats: Option<String>,
+    // Largest block size and lag of the statistics
+    pub blocks: usize,
+    pub stats_strip: bool,
+    // Cell flipped in the perturbed copy of a damage run
+    pub damage: Option<usize>,
+    // Seeds of the runs to estimate the Lyapunov exponent over
+    pub lyapunov: Option<Vec<u64>>,
+    pub derrida: Option<Derrida>,
 }
 
 impl CLIArgs {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         width: u16,
         height: u16,
@@ -40,6 +123,29 @@ impl CLIArgs {
             density,
             bitmap,
             disable_prog,
+            verbosity: Verbosity::Normal,
+            reporting: Reporting::default(),
+            boundary: Boundary::Periodic,
+            force: false,
+            seed: None,
+            sweep: None,
+            atlas: None,
+            init: None,
+            continued: false,
+            start_generation: 0,
+            every: 1,
+            lattice: Lattice::Finite,
+            expand_canvas: false,
+            lattice_width: None,
+            window_start: None,
+            track: Track::Fixed,
+            checkpoint: None,
+            stats: None,
+            blocks: DEFAULT_BLOCKS,
+            stats_strip: false,
+            damage: None,
+            lyapunov: None,
+            derrida: None,
         }
     }
 }
@@ -48,22 +154,21 @@ enum Param {
     Height,
     Width,
     Frames,
-    Rule,
     Density,
 }
 
 fn validate_float_input(param: Param, val: f64) -> f64 {
     match param {
         Param::Density => {
-            if val < 0.0 || val > 1.0 {
-                println!("Density parameter requires a value between 0.0 and 1.0");
+            if !(0.0..=1.0).contains(&val) {
+                eprintln!("Density parameter requires a value between 0.0 and 1.0");
                 exit(FAILURE_CODE);
             } else {
                 val
             }
         }
         _ => {
-            println!("Only valid for floating point inputs");
+            eprintln!("Only valid for floating point inputs");
             exit(FAILURE_CODE);
         }
     }
@@ -75,7 +180,7 @@ fn validate_integer_inputs(param: Param, val: u64) -> u64 {
             if val > 0 && val <= u16::MAX as u64 {
                 val
             } else {
-                println!(
+                eprintln!(
                     "Height parameter requires a positive 16 bit integer value (1-{})",
                     u16::MAX
                 );
@@ -86,7 +191,7 @@ fn validate_integer_inputs(param: Param, val: u64) -> u64 {
             if val > 0 && val <= u16::MAX as u64 {
                 val
             } else {
-                println!(
+                eprintln!(
                     "Width parameter requires a positive 16 bit integer value (1-{})",
                     u16::MAX
                 );
@@ -97,56 +202,394 @@ fn validate_integer_inputs(param: Param, val: u64) -> u64 {
             if val > 0 && val <= u32::MAX as u64 {
                 val
             } else {
-                println!(
+                eprintln!(
                     "Frames parameter requires a positive 32 bit integer value (1-{})",
                     u32::MAX
                 );
                 exit(FAILURE_CODE);
             }
         }
-        Param::Rule => {
-            if val <= u8::MAX as u64 {
-                val
-            } else {
-                println!(
-                    "Rule parameter requires an 8 bit integer value (0-{})",
-                    u8::MAX
+        Param::Density => {
+            eprintln!("Cannot parse density in this function");
+            exit(FAILURE_CODE);
+        }
+    }
+}
+
+fn validate_bitmap_input(input_bitmap: &str) -> BitMap {
+    match parse_bitmap(input_bitmap) {
+        Ok(bitmap) => bitmap,
+        Err(e) => {
+            eprintln!("Could not interpret bitmap: {}", e);
+            exit(FAILURE_CODE);
+        }
+    }
+}
+
+// Reads the whole of path, or of stdin if path is -
+fn read_infile(path: &str) -> std::io::Result<String> {
+    let mut bitmap_string = String::new();
+    if path == "-" {
+        std::io::stdin().read_to_string(&mut bitmap_string)?;
+    } else {
+        File::open(path)?.read_to_string(&mut bitmap_string)?;
+    }
+    Ok(bitmap_string)
+}
+
+// Cells of a given bitmap drawn: all of them, or a window of --width cells
+fn window_width(bitmap: &BitMap, width_arg: Option<u16>, matches: &clap::ArgMatches) -> u16 {
+    let cells = bitmap.size();
+    let width = match width_arg {
+        Some(width) if usize::from(width) <= cells => width,
+        Some(width) => {
+            eprintln!(
+                "--width of {} is more than the {} cells of the bitmap",
+                width, cells
+            );
+            exit(FAILURE_CODE);
+        }
+        None => match cells.try_into() {
+            Ok(width) => width,
+            Err(_) => {
+                eprintln!(
+                    "Bitmap has {} cells but can be at most {} wide, unless --width draws a window of it",
+                    cells,
+                    u16::MAX
                 );
                 exit(FAILURE_CODE);
             }
+        },
+    };
+    // These draw or measure every cell of the lattice
+    let whole = [
+        "checkpoint",
+        "damage",
+        "sweep",
+        "atlas",
+        "lyapunov",
+        "derrida",
+    ];
+    if usize::from(width) < cells {
+        if let Some(arg) = whole.iter().find(|a| matches.is_present(a)) {
+            eprintln!("--{} cannot draw a window narrower than the bitmap", arg);
+            exit(FAILURE_CODE);
         }
-        Param::Density => {
-            println!("Cannot parse density in this function");
+    }
+    width
+}
+
+fn parse_image_args(matches: &clap::ArgMatches, continued: Option<&Metadata>) -> Option<ImageRow> {
+    let parse_index = |name: &str| match matches.value_of(name) {
+        Some(i) => match i.parse() {
+            Ok(i) => i,
+            Err(_) => {
+                eprintln!("Could not interpret {} parameter", name);
+                exit(FAILURE_CODE);
+            }
+        },
+        None => 0,
+    };
+
+    let mut image = if let Some(path) = matches.value_of("continue") {
+        ImageRow {
+            // Anything right of the cells, such as a statistics strip, is not part of the run
+            columns: continued.map(|m| m.width.into()),
+            ..ImageRow::last_of(path)
+        }
+    } else if let Some(path) = matches.value_of("image") {
+        ImageRow {
+            row: parse_index("image-row"),
+            frame: parse_index("image-frame"),
+            ..ImageRow::new(path)
+        }
+    } else {
+        return None;
+    };
+
+    if let Some(t) = matches.value_of("threshold") {
+        image.threshold = match t.parse() {
+            Ok(t) => t,
+            Err(_) => {
+                eprintln!("Threshold parameter requires a value between 0 and 255");
+                exit(FAILURE_CODE);
+            }
+        };
+    }
+    Some(image)
+}
+
+fn parse_rule(spec: &str) -> Rule {
+    match spec.parse() {
+        Ok(rule) => rule,
+        Err(e) => {
+            eprintln!("Could not interpret rule parameter: {}", e);
             exit(FAILURE_CODE);
         }
     }
 }
 
-fn validate_bitmap_input(input_bitmap: &str) -> BitMap {
-    let len = input_bitmap.len();
-    let mut bitmap = BitMap::new(len.try_into().unwrap());
-    for (i, c) in input_bitmap.chars().enumerate() {
-        if c == '1' {
-            bitmap.set(i);
-        } else if c != '0' {
-            println!("Bitmap string must be a sequence of 1s and 0s");
+fn parse_rule_list(matches: &clap::ArgMatches, spec: &str) -> Vec<u8> {
+    // A single rule may be given in any notation, lists and ranges only in decimal
+    let rules: Vec<u8> = match spec.parse::<Rule>() {
+        Ok(rule) => vec![rule.number()],
+        Err(_) => match parse_int_list(spec, u8::MAX as u64) {
+            Ok(r) => r.into_iter().map(|r| r as u8).collect(),
+            Err(e) => {
+                eprintln!("Could not interpret rule parameter: {}", e);
+                exit(FAILURE_CODE);
+            }
+        },
+    };
+    if !matches.is_present("canonical") {
+        return rules;
+    }
+    let mut canonical: Vec<u8> = Vec::with_capacity(rules.len());
+    for rule in rules {
+        let rule = Rule::from(rule).canonical().number();
+        if !canonical.contains(&rule) {
+            canonical.push(rule);
+        }
+    }
+    canonical
+}
+
+fn parse_sweep_args(matches: &clap::ArgMatches, random: bool) -> Sweep {
+    let rules = parse_rule_list(matches, matches.value_of("rule").unwrap());
+
+    let densities: Vec<f64> = match parse_float_list(matches.value_of("density").unwrap()) {
+        Ok(d) => d
+            .into_iter()
+            .map(|d| validate_float_input(Param::Density, d))
+            .collect(),
+        Err(e) => {
+            eprintln!("Could not interpret density parameter: {}", e);
             exit(FAILURE_CODE);
         }
+    };
+
+    let seeds = match matches.value_of("seed") {
+        Some(s) => match parse_int_list(s, u64::MAX) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("Could not interpret seed parameter: {}", e);
+                exit(FAILURE_CODE);
+            }
+        },
+        None => Vec::new(),
+    };
+
+    // Density and seed only matter for random initial bitmaps
+    let (densities, seeds) = if random {
+        (densities, seeds)
+    } else {
+        (vec![densities[0]], Vec::new())
+    };
+
+    let threads = match matches.value_of("threads") {
+        Some(t) => match t.parse() {
+            Ok(t) if t > 0 => t,
+            _ => {
+                eprintln!("Threads parameter requires a positive integer value");
+                exit(FAILURE_CODE);
+            }
+        },
+        None => thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+
+    let template = match matches.value_of("output") {
+        Some(o) => String::from(o),
+        None => Sweep::default_template(&densities, &seeds),
+    };
+
+    let sweep = Sweep {
+        rules,
+        densities,
+        seeds,
+        template,
+        threads,
+        boundary: Boundary::Periodic,
+        reporting: Reporting::default(),
+    };
+    if let Err(e) = sweep.validate_template() {
+        eprintln!("{}", e);
+        exit(FAILURE_CODE);
     }
-    bitmap
+    sweep
+}
+
+fn parse_atlas_args(matches: &clap::ArgMatches) -> Atlas {
+    // Without an explicit rule the atlas covers every elementary rule
+    let spec = if matches.occurrences_of("rule") == 0 {
+        "0..255"
+    } else {
+        matches.value_of("rule").unwrap()
+    };
+    let rules = parse_rule_list(matches, spec);
+
+    let columns = match matches.value_of("columns").unwrap_or("16").parse() {
+        Ok(c) if c > 0 => c,
+        _ => {
+            eprintln!("Columns parameter requires a positive integer value");
+            exit(FAILURE_CODE);
+        }
+    };
+
+    Atlas { rules, columns }
 }
 
-pub fn parse_args() -> Result<CLIArgs, std::num::ParseIntError> {
+fn parse_derrida_args(matches: &clap::ArgMatches) -> Derrida {
+    let rules = parse_rule_list(matches, matches.value_of("rule").unwrap());
+
+    let samples = match matches.value_of("derrida").unwrap().parse() {
+        Ok(s) if s > 0 => s,
+        _ => {
+            eprintln!("Derrida parameter requires a positive number of samples");
+            exit(FAILURE_CODE);
+        }
+    };
+    let points = match matches.value_of("points").unwrap_or("20").parse() {
+        Ok(p) if p > 0 => p,
+        _ => {
+            eprintln!("Points parameter requires a positive integer value");
+            exit(FAILURE_CODE);
+        }
+    };
+
+    let chart = matches.value_of("output").map(String::from);
+    if let Some(chart) = &chart {
+        if chart == STDOUT || !is_still(chart) {
+            eprintln!("--derrida prints its plots to stdout and can only draw them to a .png file");
+            exit(FAILURE_CODE);
+        }
+    }
+
+    Derrida {
+        rules,
+        points,
+        samples,
+        chart,
+    }
+}
+
+fn parse_classify_args(matches: &clap::ArgMatches) -> Command {
+    let rules = parse_rule_list(matches, matches.value_of("rules").unwrap());
+    let mut classifier = Classifier::default();
+    if let Some(width) = matches.value_of("width") {
+        classifier.width = match width.parse() {
+            Ok(w) if w >= 3 => w,
+            _ => {
+                eprintln!("Width parameter requires an integer value of at least 3");
+                exit(FAILURE_CODE);
+            }
+        };
+    }
+    if let Some(generations) = matches.value_of("generations") {
+        classifier.generations = match generations.parse() {
+            Ok(g) if g >= 64 => g,
+            _ => {
+                eprintln!("Generations parameter requires an integer value of at least 64");
+                exit(FAILURE_CODE);
+            }
+        };
+    }
+    if let Some(seeds) = matches.value_of("seeds") {
+        classifier.seeds = match parse_int_list(seeds, u64::MAX) {
+            Ok(seeds) => seeds,
+            Err(e) => {
+                eprintln!("Could not interpret seeds parameter: {}", e);
+                exit(FAILURE_CODE);
+            }
+        };
+    }
+    Command::Classify {
+        rules,
+        classifier,
+        json: matches.is_present("json"),
+    }
+}
+
+pub fn parse_args() -> Result<Command, std::num::ParseIntError> {
     let matches = App::new("cellular")
         .author("W-A-James <https://github.com/W-A-James>")
         .about("A simple command-line based cellular automaton animation creator")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(
+            SubCommand::with_name("info")
+                .about("Prints a rule in every supported notation, or how a GIF built by cellular was made")
+                .arg(
+                    Arg::with_name("rerun")
+                        .long("rerun")
+                        .help("Build the GIF again from its metadata")
+                        .requires("rerun-output"),
+                )
+                .arg(
+                    Arg::with_name("rerun-output")
+                        .short("o")
+                        .long("output")
+                        .help("Output file for --rerun, or - for stdout")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .help("Overwrite the output of --rerun if it exists"),
+                )
+                .arg(
+                    Arg::with_name("target")
+                        .help("Path to a GIF, or a rule as a decimal, binary (0b...) or hexadecimal (0x...) Wolfram code, a lookup table such as '111:0 110:1 101:1 100:0 011:1 010:1 001:1 000:0', or a boolean formula over l, c and r such as 'l xor (c or r)'")
+                        .required(true)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("classify")
+                .about("Guesses the Wolfram class of rules from how runs of random initial states behave: whether they become uniform, how soon they cycle, how far damage to one cell spreads and how compressible and regular their spacetime diagrams are")
+                .arg(
+                    Arg::with_name("rules")
+                        .help("Rules to classify, as a comma separated list of values and inclusive ranges such as 30,90,100..110")
+                        .default_value("0..255")
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("width")
+                        .short("w")
+                        .long("width")
+                        .help("Width of the lattice of each run. Odd widths keep additive rules from dying out [default: 149]")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("generations")
+                        .short("g")
+                        .long("generations")
+                        .help("Length of each run [default: 400]")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("seeds")
+                        .short("s")
+                        .long("seeds")
+                        .help("Seeds of the random initial states, one run each [default: 1..8]")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("canonical")
+                        .long("canonical")
+                        .help("Replace each rule by the canonical representative of its class under mirroring and complementing, dropping duplicates"),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Print one JSON object per rule"),
+                ),
+        )
         .arg(
             Arg::with_name("width")
                 .short("w")
                 .long("width")
-                .help("Specifies width of output image")
+                .help("Specifies width of output image. With --bitmap or --infile, only a window this many cells wide of the whole bitmap is drawn")
                 .takes_value(true)
-                .required_unless_one(&["infile", "bitmap"])
+                .required_unless_one(&["infile", "bitmap", "image", "continue", "equivalents", "resume"])
         )
         .arg(
             Arg::with_name("height")
@@ -154,14 +597,14 @@ pub fn parse_args() -> Result<CLIArgs, std::num::ParseIntError> {
                 .long("height")
                 .help("Specifies height of output image")
                 .takes_value(true)
-                .required(true),
+                .required_unless_one(&["equivalents", "resume", "derrida"]),
         )
         .arg(
             Arg::with_name("frames")
                 .short("f")
                 .long("frames")
                 .help("Number of frames in final animation")
-                .required(true)
+                .required_unless_one(&["equivalents", "resume", "derrida", "damage", "lyapunov"])
                 .takes_value(true),
         )
         .arg(
@@ -175,32 +618,75 @@ pub fn parse_args() -> Result<CLIArgs, std::num::ParseIntError> {
             Arg::with_name("bitmap")
             .short("b")
             .long("bitmap")
-            .help("Input bitmap as string of 1s and 0s")
+            .help("Input bitmap as string of 1s and 0s. Also accepts ./* and _/X, or hex: and base64: encoded bits")
             .takes_value(true)
             .conflicts_with("density")
-            .conflicts_with("width")
             )
         .arg(
             Arg::with_name("infile")
             .short("i")
             .long("infile")
-            .help("path to file containing initial bitmap as string of 1s and 0s")
+            .help("path to file containing initial bitmap as string of 1s and 0s, or - to read from stdin. Whitespace and # comments are ignored, and cells may also be written as ./* or _/X, or as hex: or base64: encoded bits")
+            .takes_value(true)
+            .conflicts_with("bitmap")
+            )
+        .arg(
+            Arg::with_name("image")
+            .long("image")
+            .help("Path to a GIF, PNG or PBM image to take the initial bitmap from. Dark pixels of the chosen row become occupied cells, and the row is resized to --width if given")
+            .takes_value(true)
+            .conflicts_with_all(&["bitmap", "infile", "init", "density", "seed"])
+            )
+        .arg(
+            Arg::with_name("image-row")
+            .long("image-row")
+            .help("Row of --image to use. Negative values count back from the bottom [default: 0]")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .requires("image")
+            )
+        .arg(
+            Arg::with_name("image-frame")
+            .long("image-frame")
+            .help("Frame of a GIF --image to use. Negative values count back from the end [default: 0]")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .requires("image")
+            )
+        .arg(
+            Arg::with_name("threshold")
+            .long("threshold")
+            .help("Pixels of --image or --continue with a luminance below this value (0-255) become occupied cells [default: 128]")
+            .takes_value(true)
+            )
+        .arg(
+            Arg::with_name("continue")
+            .long("continue")
+            .help("Continue the evolution from the last row of a GIF built by cellular, starting at the generation after it. The rule, boundary and --every of the GIF are kept unless given")
+            .takes_value(true)
+            .conflicts_with_all(&["bitmap", "infile", "init", "density", "seed", "image", "sweep", "atlas"])
+            )
+        .arg(
+            Arg::with_name("init")
+            .long("init")
+            .help("Generator for the initial bitmap: random[:density], single[:index] for one occupied cell (centred by default), periodic:<pattern> to tile a string of 1s and 0s, blocks:<count>:<width>[:density] for blocks of noise in an empty background, markov:<p_birth>:<p_death> for correlated noise, or ether[:<gliders>] for the rule 110 background with gliders such as A,B,A")
             .takes_value(true)
             .conflicts_with("bitmap")
-            .conflicts_with("width")
+            .conflicts_with("infile")
+            .conflicts_with("density")
             )
         .arg(
             Arg::with_name("output")
                 .short("o")
                 .long("output")
-                .help("Specifies output file. Defaults to output_w<width>_h<height>_f<frames>_r<rule>.gif")
+                .help("Specifies output file, or - to write the GIF to stdout. Defaults to output_w<width>_h<height>_f<frames>_r<rule>.gif")
                 .takes_value(true),
         )
         .arg(
             Arg::with_name("rule")
                 .short("r")
                 .long("rule")
-                .help("8 bit unsigned integer which specifies the cellular automaton to simulate")
+                .help("Cellular automaton to simulate, as an 8 bit Wolfram code in decimal, binary (0b...) or hexadecimal (0x...), a lookup table such as '111:0 110:1 ... 000:0', or a boolean formula over l, c and r such as 'l xor (c or r)'")
                 .default_value("110"),
         )
         .arg(
@@ -209,87 +695,442 @@ pub fn parse_args() -> Result<CLIArgs, std::num::ParseIntError> {
                 .long("no-prog")
                 .help("disable progress bar")
             )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .help("Print nothing but errors")
+                .conflicts_with("verbose")
+            )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .help("Print a summary of the time taken, generations per second and bytes written once done")
+            )
+        .arg(
+            Arg::with_name("boundary")
+                .long("boundary")
+                .help("What lies beyond the edges of the lattice: periodic (the edges neighbour each other), fixed:0 or fixed:1 (cells always in that state) or reflect (copies of the edge cells) [default: periodic]")
+                .takes_value(true)
+                .conflicts_with("atlas")
+            )
+        .arg(
+            Arg::with_name("start-generation")
+                .long("start-generation")
+                .visible_alias("skip")
+                .help("Start the animation at this generation of the initial bitmap, e.g. once the transient has died out. Additive rules such as 60, 90, 102 and 150 on periodic lattices jump straight there, others are simulated up to it without drawing, which counts in the progress bar")
+                .takes_value(true)
+                .conflicts_with_all(&["resume", "sweep", "atlas", "lyapunov", "derrida"])
+            )
+        .arg(
+            Arg::with_name("every")
+                .long("every")
+                .help("Draw every this many generations, each row this many generations after the one above it [default: 1]")
+                .takes_value(true)
+                .conflicts_with_all(&["resume", "sweep", "atlas", "checkpoint", "damage", "lyapunov", "derrida"])
+            )
+        .arg(
+            Arg::with_name("hashlife")
+                .long("hashlife")
+                .help("Run on an unbounded lattice of empty cells, drawing the --width cells the initial bitmap covers, with a memoized engine that makes millions of generations fast for rules such as 110. Best with --every and --start-generation. Rules that fill empty space, the odd ones, cannot run this way")
+                .conflicts_with_all(&["boundary", "resume", "sweep", "atlas", "checkpoint", "damage", "lyapunov", "derrida"])
+            )
+        .arg(
+            Arg::with_name("growing")
+                .long("growing")
+                .help("Run on an unbounded lattice of empty cells that grows by one cell a side every generation, as far as the rule can reach, so that patterns never wrap around. Rules that fill empty space, the odd ones, cannot run this way")
+                .conflicts_with_all(&["boundary", "hashlife", "resume", "sweep", "atlas", "checkpoint", "damage", "lyapunov", "derrida"])
+            )
+        .arg(
+            Arg::with_name("canvas")
+                .long("canvas")
+                .help("How much of an unbounded lattice from --growing or --hashlife to draw: the --width cells the initial bitmap covers, or every cell the run can reach, two more for every generation up to the last one drawn")
+                .takes_value(true)
+                .possible_values(&["fixed", "expand"])
+            )
+        .arg(
+            Arg::with_name("lattice-width")
+                .long("lattice-width")
+                .help("Number of cells to simulate, of which only a window of --width cells is drawn, so that patterns far from the window cannot wrap around into it [default: --width]")
+                .takes_value(true)
+                .conflicts_with_all(&["bitmap", "infile", "image", "continue", "resume", "sweep", "atlas", "checkpoint", "damage", "lyapunov", "derrida"])
+            )
+        .arg(
+            Arg::with_name("window-start")
+                .long("window-start")
+                .help("Leftmost cell of the window drawn at the first frame, counting from the first cell of the initial bitmap [default: the window is centred on it]")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .conflicts_with_all(&["canvas", "resume", "sweep", "atlas", "checkpoint", "damage", "lyapunov", "derrida"])
+            )
+        .arg(
+            Arg::with_name("track")
+                .long("track")
+                .help("Move the window drawn with the pattern: mass keeps it centred on the occupied cells, a velocity such as 2/3 or -0.5 moves it that many cells a generation, e.g. to follow a glider [default: fixed]")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .conflicts_with_all(&["canvas", "resume", "sweep", "atlas", "checkpoint", "damage", "lyapunov", "derrida"])
+            )
+        .arg(
+            Arg::with_name("force")
+                .long("force")
+                .help("Overwrite outputs that already exist instead of refusing to start")
+            )
+        .arg(
+            Arg::with_name("checkpoint")
+                .long("checkpoint")
+                .help("Write a checkpoint to <output>.checkpoint every this many frames, so that a run that crashes or is interrupted can carry on with --resume")
+                .takes_value(true)
+                .conflicts_with_all(&["sweep", "atlas"])
+            )
+        .arg(
+            Arg::with_name("resume")
+                .long("resume")
+                .help("Carry on a run from a checkpoint file, appending to the frames it already wrote. If they are gone, or -o is given, the remaining frames go to a new GIF instead")
+                .takes_value(true)
+                .conflicts_with_all(&["bitmap", "infile", "image", "continue", "init", "sweep", "atlas", "checkpoint"])
+            )
+        .arg(
+            Arg::with_name("stats")
+                .long("stats")
+                .help("Write the density, number of runs of occupied cells, block entropies and spatial autocorrelation of each generation to this file as it is drawn, or to stdout with -. Files ending in .json or .jsonl get one JSON object per line, anything else CSV")
+                .takes_value(true)
+                .conflicts_with_all(&["sweep", "atlas", "checkpoint"])
+            )
+        .arg(
+            Arg::with_name("blocks")
+                .long("blocks")
+                .help("Largest block size for block entropies and lag for autocorrelations in --stats and --stats-strip, up to 16 [default: 4]")
+                .takes_value(true)
+            )
+        .arg(
+            Arg::with_name("stats-strip")
+                .long("stats-strip")
+                .help("Draw bars for the density and block entropy of each generation to the right of the animation")
+                .conflicts_with_all(&["sweep", "atlas"])
+            )
+        .arg(
+            Arg::with_name("damage")
+                .long("damage")
+                .help("Evolve a second copy of the initial bitmap with this cell flipped and draw where the two copies differ in red, one row per generation for --height generations. Prints the Hamming distance and the extent of the damage either side of the cell for each generation as CSV")
+                .takes_value(true)
+                .conflicts_with_all(&["sweep", "atlas", "stats", "stats-strip", "checkpoint", "sidecar", "report"])
+            )
+        .arg(
+            Arg::with_name("lyapunov")
+                .long("lyapunov")
+                .help("Estimate the maximal Lyapunov exponent of the rule from damage to the cell given by --damage, or the middle cell, over --height generations of a random run for each of these seeds, e.g. 1..100")
+                .takes_value(true)
+                .conflicts_with_all(&["bitmap", "infile", "image", "continue", "seed", "sweep", "atlas", "stats", "stats-strip", "checkpoint", "sidecar", "report"])
+            )
+        .arg(
+            Arg::with_name("derrida")
+                .long("derrida")
+                .help("Print the Derrida plot of each rule as CSV: the expected distance between two random states of --width cells after one step, against the distance between them, averaged over this many pairs at each distance. The rule then accepts a comma separated list of values and inclusive ranges. Outputs given with -o must be PNG charts of every plot")
+                .takes_value(true)
+                .conflicts_with_all(&["bitmap", "infile", "image", "continue", "init", "sweep", "atlas", "stats", "stats-strip", "checkpoint", "sidecar", "report", "damage", "lyapunov"])
+            )
+        .arg(
+            Arg::with_name("points")
+                .long("points")
+                .help("Number of steps between initial distances 0 and 1 in --derrida [default: 20]")
+                .takes_value(true)
+                .requires("derrida")
+            )
+        .arg(
+            Arg::with_name("sidecar")
+                .long("sidecar")
+                .help("Write a .json report of the parameters, timings, output size, final density and any cycle found next to each output")
+                .conflicts_with("atlas")
+            )
+        .arg(
+            Arg::with_name("report")
+                .long("report")
+                .help("Print the report of each run to stdout instead of showing a progress bar")
+                .takes_value(true)
+                .possible_values(&["json"])
+                .conflicts_with("atlas")
+            )
+        .arg(
+            Arg::with_name("seed")
+                .short("s")
+                .long("seed")
+                .help("Seed for the random initial bitmap. Runs with the same seed and density are identical")
+                .takes_value(true)
+                .conflicts_with("bitmap")
+                .conflicts_with("infile")
+            )
+        .arg(
+            Arg::with_name("sweep")
+                .long("sweep")
+                .help("Build one animation per combination of rule, density and seed. Each of them then accepts a comma separated list of values and inclusive ranges such as 0..255 or 0.1..0.9:0.1. The output may be a template containing {rule}, {density}, {seed}, {width}, {height} and {frames}")
+            )
+        .arg(
+            Arg::with_name("threads")
+                .long("threads")
+                .help("Number of animations built at once in sweep mode. Defaults to the number of available cores")
+                .takes_value(true)
+                .requires("sweep")
+            )
+        .arg(
+            Arg::with_name("atlas")
+                .long("atlas")
+                .help("Tile the evolution of several rules from the same initial bitmap into one labeled image. The rule then accepts a comma separated list of values and inclusive ranges, and defaults to all 256 rules. Outputs ending in .png are still images, otherwise an animated GIF is built")
+                .conflicts_with("sweep")
+            )
+        .arg(
+            Arg::with_name("columns")
+                .long("columns")
+                .help("Number of tiles per row of the atlas [default: 16]")
+                .takes_value(true)
+                .requires("atlas")
+            )
+        .arg(
+            Arg::with_name("canonical")
+                .long("canonical")
+                .help("In sweep and atlas mode, replace each rule by the canonical representative of its class under mirroring and complementing, dropping duplicates. All 256 rules reduce to 88")
+            )
+        .arg(
+            Arg::with_name("equivalents")
+                .long("equivalents")
+                .help("Print the mirror, complement and mirror-complement of the rule and the canonical representative of its equivalence class, then exit")
+            )
         .get_matches();
 
-    let height: u16 = match matches.value_of("height").unwrap().parse() {
-        Ok(h) => validate_integer_inputs(Param::Height, h)
-            .try_into()
-            .unwrap(),
-        Err(_) => {
-            println!("Could not interpret height parameter");
+    if let Some(info) = matches.subcommand_matches("info") {
+        let target = info.value_of("target").unwrap();
+        if Path::new(target).is_file() {
+            return Ok(Command::GifInfo {
+                path: String::from(target),
+                rerun: info.value_of("rerun-output").map(String::from),
+                force: info.is_present("force"),
+            });
+        }
+        if info.is_present("rerun") || target.ends_with(".gif") {
+            eprintln!("Could not find a GIF at {}", target);
             exit(FAILURE_CODE);
         }
+        return Ok(Command::Info(parse_rule(target)));
+    }
+
+    if let Some(classify) = matches.subcommand_matches("classify") {
+        return Ok(parse_classify_args(classify));
+    }
+
+    if matches.is_present("equivalents") {
+        return Ok(Command::Equivalents(parse_rule(
+            matches.value_of("rule").unwrap(),
+        )));
+    }
+
+    let verbosity = if matches.is_present("quiet") {
+        Verbosity::Quiet
+    } else if matches.is_present("verbose") {
+        Verbosity::Verbose
+    } else {
+        Verbosity::Normal
     };
 
-    let steps = match matches.value_of("frames").unwrap().parse() {
-        Ok(s) => validate_integer_inputs(Param::Frames, s)
+    if let Some(checkpoint) = matches.value_of("resume") {
+        return Ok(Command::Resume {
+            checkpoint: String::from(checkpoint),
+            segment: matches.value_of("output").map(String::from),
+            force: matches.is_present("force"),
+            disable_prog: matches.is_present("no-prog") || verbosity == Verbosity::Quiet,
+            verbosity,
+        });
+    }
+
+    // Derrida plots only look one step ahead, so they need neither
+    let height: u16 = match matches.value_of("height").unwrap_or("1").parse() {
+        Ok(h) => validate_integer_inputs(Param::Height, h)
             .try_into()
             .unwrap(),
         Err(_) => {
-            println!("Could not interpret frames parameter");
+            eprintln!("Could not interpret height parameter");
             exit(FAILURE_CODE);
         }
     };
 
-    let rule = match matches.value_of("rule").unwrap().parse() {
-        Ok(r) => validate_integer_inputs(Param::Rule, r).try_into().unwrap(),
+    let steps = match matches.value_of("frames").unwrap_or("1").parse() {
+        Ok(s) => validate_integer_inputs(Param::Frames, s)
+            .try_into()
+            .unwrap(),
         Err(_) => {
-            println!("Could not interpret rule parameter");
+            eprintln!("Could not interpret frames parameter");
             exit(FAILURE_CODE);
         }
     };
 
-    let probability_density = match matches.value_of("density").unwrap().parse() {
-        Ok(d) => validate_float_input(Param::Density, d),
+    // Animations built by cellular record how they were run, which --continue carries on
+    // with unless told otherwise
+    let continued = matches
+        .value_of("continue")
+        .and_then(|path| Metadata::read(path).ok());
+    let image = parse_image_args(&matches, continued.as_ref());
+    let random = !matches.is_present("bitmap") && !matches.is_present("infile") && image.is_none();
+    let width_arg: Option<u16> = matches.value_of("width").map(|w| match w.parse() {
+        Ok(w) => validate_integer_inputs(Param::Width, w).try_into().unwrap(),
         Err(_) => {
-            println!("Could not interpret density parameter");
+            eprintln!("Could not interpret width parameter");
             exit(FAILURE_CODE);
         }
-    };
-
-    let random = !matches.is_present("bitmap") && !matches.is_present("infile");
+    });
     let width: u16;
     let bitmap: Option<BitMap>;
     if random {
-        width = match matches.value_of("width").unwrap().parse() {
-            Ok(w) => validate_integer_inputs(Param::Width, w).try_into().unwrap(),
-            Err(_) => {
-                println!("Could not interpret width parameter");
-                exit(FAILURE_CODE);
-            }
-        };
+        width = width_arg.unwrap();
         bitmap = None;
     } else {
-        if matches.is_present("bitmap") {
+        if let Some(image) = &image {
+            match image.to_bitmap(width_arg) {
+                Ok(bmp) => {
+                    width = bmp.size().try_into().unwrap();
+                    bitmap = Some(bmp);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    exit(FAILURE_CODE)
+                }
+            }
+        } else if matches.is_present("bitmap") {
             let bmp = validate_bitmap_input(matches.value_of("bitmap").unwrap());
-            width = bmp.size().try_into().unwrap();
+            width = window_width(&bmp, width_arg, &matches);
             bitmap = Some(bmp);
         } else {
-            match File::open(matches.value_of("infile").unwrap()) {
-                Ok(mut f) => {
-                    let mut bitmap_string = String::new();
-                    f.read_to_string(&mut bitmap_string).unwrap();
-                    let bmp = validate_bitmap_input(&bitmap_string.strip_suffix("\n").unwrap());
-                    width = bmp.size().try_into().unwrap();
+            match read_infile(matches.value_of("infile").unwrap()) {
+                Ok(bitmap_string) => {
+                    let bmp = validate_bitmap_input(&bitmap_string);
+                    width = window_width(&bmp, width_arg, &matches);
                     bitmap = Some(bmp);
                 }
                 Err(e) => {
-                    println!("{}", e);
+                    eprintln!("{}", e);
                     exit(FAILURE_CODE)
                 }
             }
         }
     }
 
-    let disable_prog = matches.is_present("no-prog");
+    if matches.value_of("stats") == Some(STDOUT)
+        && (matches.value_of("output") == Some(STDOUT) || matches.is_present("report"))
+    {
+        eprintln!("--stats - cannot share stdout with the output or --report");
+        exit(FAILURE_CODE);
+    }
+
+    if matches.value_of("output") == Some(STDOUT) {
+        let file_only = ["sweep", "atlas", "sidecar", "report", "checkpoint"];
+        if let Some(arg) = file_only.iter().find(|a| matches.is_present(a)) {
+            eprintln!("--{} cannot be used when writing to stdout", arg);
+            exit(FAILURE_CODE);
+        }
+    }
+
+    let boundary = match matches.value_of("boundary") {
+        Some(spec) => match spec.parse() {
+            Ok(boundary) => boundary,
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(FAILURE_CODE);
+            }
+        },
+        None => continued
+            .as_ref()
+            .map_or(Boundary::Periodic, |m| m.boundary),
+    };
+    let reporting = Reporting {
+        sidecar: matches.is_present("sidecar"),
+        stdout: matches.is_present("report"),
+    };
+    // The report takes the place of the progress bar on stdout
+    let disable_prog =
+        matches.is_present("no-prog") || reporting.stdout || verbosity == Verbosity::Quiet;
+
+    let init = match matches.value_of("init") {
+        Some(spec) => match spec.parse() {
+            Ok(init) => Some(init),
+            Err(e) => {
+                eprintln!("Could not interpret init parameter: {}", e);
+                exit(FAILURE_CODE);
+            }
+        },
+        None => None,
+    };
+
+    if matches.is_present("sweep") {
+        let mut sweep = parse_sweep_args(&matches, random);
+        sweep.boundary = boundary;
+        sweep.reporting = reporting;
+        let mut args = CLIArgs::new(
+            width,
+            height,
+            steps,
+            random,
+            &sweep.template,
+            sweep.rules[0],
+            sweep.densities[0],
+            bitmap,
+            disable_prog,
+        );
+        args.seed = sweep.seeds.first().copied();
+        args.force = matches.is_present("force");
+        args.verbosity = verbosity;
+        args.sweep = Some(sweep);
+        args.init = init;
+        return Ok(Command::Render(Box::new(args)));
+    }
+
+    let atlas = if matches.is_present("atlas") {
+        Some(parse_atlas_args(&matches))
+    } else {
+        None
+    };
+
+    let derrida = if matches.is_present("derrida") {
+        Some(parse_derrida_args(&matches))
+    } else {
+        None
+    };
+
+    let rule = match (&atlas, &derrida) {
+        (Some(atlas), _) => atlas.rules[0],
+        (_, Some(derrida)) => derrida.rules[0],
+        _ => match &continued {
+            Some(metadata) if matches.occurrences_of("rule") == 0 => metadata.rule,
+            _ => parse_rule(matches.value_of("rule").unwrap()).number(),
+        },
+    };
+
+    let probability_density = match matches.value_of("density").unwrap().parse() {
+        Ok(d) => validate_float_input(Param::Density, d),
+        Err(_) => {
+            eprintln!("Could not interpret density parameter");
+            exit(FAILURE_CODE);
+        }
+    };
+
+    let seed = match matches.value_of("seed") {
+        Some(s) => match s.parse() {
+            Ok(s) => Some(s),
+            Err(_) => {
+                eprintln!("Seed parameter requires a 64 bit unsigned integer value");
+                exit(FAILURE_CODE);
+            }
+        },
+        None => None,
+    };
 
     let output = if matches.is_present("output") {
         String::from(matches.value_of("output").unwrap())
+    } else if atlas.is_some() {
+        format!("atlas_w{}_h{}_f{}.gif", width, height, steps)
     } else {
         format!("output_w{}_h{}_f{}_r{}.gif", width, height, steps, rule)
     };
 
-    Ok(CLIArgs::new(
+    let mut args = CLIArgs::new(
         width,
         height,
         steps,
@@ -299,5 +1140,141 @@ pub fn parse_args() -> Result<CLIArgs, std::num::ParseIntError> {
         probability_density,
         bitmap,
         disable_prog,
-    ))
+    );
+    args.seed = seed;
+    args.reporting = reporting;
+    args.boundary = boundary;
+    args.force = matches.is_present("force");
+    args.verbosity = verbosity;
+    args.atlas = atlas;
+    args.derrida = derrida;
+    args.init = init;
+    args.continued = matches.is_present("continue");
+    if let Some(every) = matches.value_of("every") {
+        args.every = match every.parse() {
+            Ok(every) if every > 0 => every,
+            _ => {
+                eprintln!("--every must be a number of generations of at least 1");
+                exit(FAILURE_CODE);
+            }
+        };
+    } else if let Some(metadata) = &continued {
+        args.every = metadata.stride;
+    }
+    if matches.is_present("hashlife") {
+        args.lattice = Lattice::HashLife;
+    } else if matches.is_present("growing") {
+        args.lattice = Lattice::Growing;
+    }
+    if args.lattice != Lattice::Finite {
+        if let Err(e) = check_quiescent(rule.into()) {
+            eprintln!("{}", e);
+            exit(FAILURE_CODE);
+        }
+    }
+    args.expand_canvas = matches.value_of("canvas") == Some("expand");
+    if matches.is_present("canvas") && args.lattice == Lattice::Finite {
+        eprintln!("--canvas only applies to the unbounded lattices of --growing and --hashlife");
+        exit(FAILURE_CODE);
+    }
+    args.lattice_width = matches
+        .value_of("lattice-width")
+        .map(|cells| match cells.parse() {
+            Ok(cells) if cells >= u64::from(width) => cells,
+            _ => {
+                eprintln!(
+                    "--lattice-width must be a number of cells of at least the width of {}",
+                    width
+                );
+                exit(FAILURE_CODE);
+            }
+        });
+    args.window_start = matches
+        .value_of("window-start")
+        .map(|cell| match cell.parse() {
+            Ok(cell) => cell,
+            Err(_) => {
+                eprintln!("Could not interpret window-start parameter");
+                exit(FAILURE_CODE);
+            }
+        });
+    if let Some(track) = matches.value_of("track") {
+        args.track = match track.parse() {
+            Ok(track) => track,
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(FAILURE_CODE);
+            }
+        };
+    }
+    if let Some(start) = matches.value_of("start-generation") {
+        args.start_generation = match start.parse() {
+            Ok(start) => start,
+            Err(_) => {
+                eprintln!("Could not interpret start-generation parameter");
+                exit(FAILURE_CODE);
+            }
+        };
+    }
+    args.stats = matches.value_of("stats").map(String::from);
+    args.stats_strip = matches.is_present("stats-strip");
+    if args.stats_strip && width > StatsStrip::MAX_CELLS {
+        eprintln!(
+            "--stats-strip needs room in the image, so the width can be at most {}",
+            StatsStrip::MAX_CELLS
+        );
+        exit(FAILURE_CODE);
+    }
+    if args.stats_strip && is_still(&args.output) {
+        eprintln!("--stats-strip only applies to GIF animations");
+        exit(FAILURE_CODE);
+    }
+    if let Some(blocks) = matches.value_of("blocks") {
+        args.blocks = match blocks.parse() {
+            Ok(k) if (1..=MAX_BLOCK).contains(&k) => k,
+            _ => {
+                eprintln!("--blocks must be between 1 and {}", MAX_BLOCK);
+                exit(FAILURE_CODE);
+            }
+        };
+    }
+    args.damage = matches.value_of("damage").map(|cell| match cell.parse() {
+        Ok(cell) if cell < width as usize => cell,
+        _ => {
+            eprintln!("--damage must be a cell index below the width of {}", width);
+            exit(FAILURE_CODE);
+        }
+    });
+    args.lyapunov =
+        matches
+            .value_of("lyapunov")
+            .map(|seeds| match parse_int_list(seeds, u64::MAX) {
+                Ok(seeds) => seeds,
+                Err(e) => {
+                    eprintln!("Could not interpret lyapunov parameter: {}", e);
+                    exit(FAILURE_CODE);
+                }
+            });
+    if args.damage.is_some() && args.output == STDOUT {
+        eprintln!("--damage prints its measurements to stdout, so it needs an output file");
+        exit(FAILURE_CODE);
+    }
+    args.checkpoint = matches
+        .value_of("checkpoint")
+        .map(|every| match every.parse() {
+            Ok(every) if every > 0 && !is_still(&args.output) => every,
+            Ok(0) => {
+                eprintln!("--checkpoint needs at least 1 frame between checkpoints");
+                exit(FAILURE_CODE);
+            }
+            Ok(_) => {
+                eprintln!("--checkpoint only applies to GIF animations");
+                exit(FAILURE_CODE);
+            }
+            Err(_) => {
+                eprintln!("Could not interpret checkpoint parameter");
+                exit(FAILURE_CODE);
+            }
+        });
+    Ok(Command::Render(Box::new(args)))
 }
@@ -0,0 +1,462 @@
+use crate::image_manip::bitmap::{BitMap, Boundary};
+use crate::simulation::Simulation;
+
+use gif::{Encoder, EncodingError, Frame};
+use rand::seq::index;
+use rand::Rng;
+
+use std::io::{self, Write};
+
+// White where both copies hold empty cells, black where both hold occupied cells and
+// red where they differ. GIF colour tables need a power of two entries
+const PALETTE: [u8; 12] = [
+    0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0xE0, 0x20, 0x20, 0xFF, 0xFF, 0xFF,
+];
+const AGREE_EMPTY: u8 = 0;
+const AGREE_OCCUPIED: u8 = 1;
+const DIFFER: u8 = 2;
+
+// Two copies of a run that start out differing in one cell, evolved side by side to see
+// how the difference, or damage, spreads
+pub struct Damage {
+    original: Simulation,
+    perturbed: Simulation,
+    cell: usize,
+    // Generation the cell was flipped at
+    start: u64,
+}
+
+impl Damage {
+    // The perturbed copy is simulation with cell flipped
+    pub fn new(simulation: Simulation, cell: usize) -> Result<Damage, String> {
+        if cell >= simulation.width() {
+            return Err(format!(
+                "Cell {} is outside of a lattice of {} cells",
+                cell,
+                simulation.width()
+            ));
+        }
+        let mut cells = simulation.cells().clone();
+        if cells.get(cell) == 1 {
+            cells.unset(cell);
+        } else {
+            cells.set(cell);
+        }
+        let perturbed = Simulation::new(simulation.rule(), simulation.boundary(), cells)
+            .starting_at(simulation.generation());
+        Ok(Damage {
+            start: simulation.generation(),
+            original: simulation,
+            perturbed,
+            cell,
+        })
+    }
+
+    pub fn cell(&self) -> usize {
+        self.cell
+    }
+}
+
+// Both copies of a generation and how far apart they are
+#[derive(Debug, Clone)]
+pub struct DamageGeneration {
+    pub index: u64,
+    // Generation the cell was flipped at
+    pub start: u64,
+    pub original: BitMap,
+    pub perturbed: BitMap,
+    // Number of cells that differ
+    pub hamming: u64,
+    // How many cells the damage reaches to the left (lower indices) and right of the
+    // flipped cell, or None once the copies agree again
+    pub left: Option<u64>,
+    pub right: Option<u64>,
+}
+
+impl DamageGeneration {
+    pub fn csv_header() -> &'static str {
+        "generation,hamming,left,right"
+    }
+
+    pub fn to_csv(&self) -> String {
+        let extent = |e: Option<u64>| e.map(|e| e.to_string()).unwrap_or_default();
+        format!(
+            "{},{},{},{}",
+            self.index,
+            self.hamming,
+            extent(self.left),
+            extent(self.right)
+        )
+    }
+
+    // Average speeds of the left and right damage fronts since the cell was flipped, in
+    // cells per generation
+    pub fn front_speeds(&self) -> Option<(f64, f64)> {
+        let elapsed = self.index.saturating_sub(self.start);
+        match (self.left, self.right) {
+            (Some(left), Some(right)) if elapsed > 0 => {
+                Some((left as f64 / elapsed as f64, right as f64 / elapsed as f64))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Iterator for Damage {
+    type Item = DamageGeneration;
+
+    fn next(&mut self) -> Option<DamageGeneration> {
+        let original = self.original.next()?.cells;
+        let perturbed = self.perturbed.next()?.cells;
+        let len = original.size();
+        let periodic = self.original.boundary() == Boundary::Periodic;
+        let mut hamming = 0;
+        let mut extents: Option<(u64, u64)> = None;
+        for i in (0..len).filter(|&i| original.get(i) != perturbed.get(i)) {
+            hamming += 1;
+            // Damage can go either way around a periodic lattice, so it is measured
+            // along the shorter way
+            let offset = (i + len - self.cell) % len;
+            let (left, right) = if periodic {
+                if offset <= len / 2 {
+                    (0, offset)
+                } else {
+                    (len - offset, 0)
+                }
+            } else if i < self.cell {
+                (self.cell - i, 0)
+            } else {
+                (0, i - self.cell)
+            };
+            let (l, r) = extents.unwrap_or((0, 0));
+            extents = Some((l.max(left as u64), r.max(right as u64)));
+        }
+        Some(DamageGeneration {
+            index: self.original.generation(),
+            start: self.start,
+            original,
+            perturbed,
+            hamming,
+            left: extents.map(|e| e.0),
+            right: extents.map(|e| e.1),
+        })
+    }
+}
+
+// Pattern of the neighbourhood of cell i as in BitMap::rule_step_with_boundary, along with
+// the cells it depends on and the bits of the pattern each of them sets
+fn neighbourhood(cells: &[u8], i: usize, boundary: Boundary) -> (u8, Vec<(usize, u8)>) {
+    let len = cells.len();
+    let beyond = |edge: usize, wrapped: usize| match boundary {
+        Boundary::Periodic => (Some(wrapped), cells[wrapped] == 1),
+        Boundary::Fixed(state) => (None, state),
+        Boundary::Reflect => (Some(edge), cells[edge] == 1),
+    };
+    let next = if i + 1 == len {
+        beyond(i, 0)
+    } else {
+        (Some(i + 1), cells[i + 1] == 1)
+    };
+    let previous = if i == 0 {
+        beyond(i, len - 1)
+    } else {
+        (Some(i - 1), cells[i - 1] == 1)
+    };
+    let mut pattern = 0;
+    let mut depends: Vec<(usize, u8)> = Vec::with_capacity(3);
+    for (mask, (cell, occupied)) in [
+        (0b100, next),
+        (0b010, (Some(i), cells[i] == 1)),
+        (0b001, previous),
+    ]
+    .iter()
+    .copied()
+    {
+        if occupied {
+            pattern |= mask;
+        }
+        // A cell can appear more than once on small or reflecting lattices
+        match (cell, depends.iter_mut().find(|(c, _)| Some(*c) == cell)) {
+            (Some(_), Some((_, bits))) => *bits |= mask,
+            (Some(cell), None) => depends.push((cell, mask)),
+            (None, _) => {}
+        }
+    }
+    (pattern, depends)
+}
+
+// Estimates the maximal Lyapunov exponent of a run by following every path along which
+// damage to cell can travel through the rule's Boolean derivatives (Bagnoli, Rechtman
+// and Ruffo, 1992). Returns None if every path dies out within the given generations
+pub fn lyapunov(simulation: Simulation, cell: usize, generations: u64) -> Option<f64> {
+    let rule = simulation.rule().number();
+    let boundary = simulation.boundary();
+    let len = simulation.width();
+    if cell >= len || generations == 0 {
+        return None;
+    }
+    let mut simulation = simulation;
+    // Number of damage paths reaching each cell, normalised to sum to 1
+    let mut paths = vec![0.0; len];
+    paths[cell] = 1.0;
+    let mut log_growth = 0.0;
+    for _ in 0..generations {
+        let cells = simulation.cells().to_bit_vec();
+        let mut next = vec![0.0; len];
+        for (i, count) in next.iter_mut().enumerate() {
+            let (pattern, depends) = neighbourhood(&cells, i, boundary);
+            let out = (rule >> pattern) & 1;
+            for (j, bits) in depends {
+                if (rule >> (pattern ^ bits)) & 1 != out {
+                    *count += paths[j];
+                }
+            }
+        }
+        let total: f64 = next.iter().sum();
+        if total == 0.0 {
+            return None;
+        }
+        log_growth += total.ln();
+        paths = next.iter().map(|p| p / total).collect();
+        simulation.step();
+    }
+    Some(log_growth / generations as f64)
+}
+
+// Lyapunov exponents of an ensemble of runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct LyapunovEstimate {
+    // Mean and standard deviation over the runs whose damage survived, if any did
+    pub mean: Option<f64>,
+    pub std_dev: Option<f64>,
+    pub runs: usize,
+    // Number of runs whose damage died out
+    pub died: usize,
+}
+
+pub fn lyapunov_ensemble<I: IntoIterator<Item = Simulation>>(
+    simulations: I,
+    cell: usize,
+    generations: u64,
+) -> LyapunovEstimate {
+    let exponents: Vec<Option<f64>> = simulations
+        .into_iter()
+        .map(|simulation| lyapunov(simulation, cell, generations))
+        .collect();
+    let survived: Vec<f64> = exponents.iter().flatten().copied().collect();
+    let (mean, std_dev) = if survived.is_empty() {
+        (None, None)
+    } else {
+        let n = survived.len() as f64;
+        let mean = survived.iter().sum::<f64>() / n;
+        let variance = survived.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+        (Some(mean), Some(variance.sqrt()))
+    };
+    LyapunovEstimate {
+        mean,
+        std_dev,
+        runs: exponents.len(),
+        died: exponents.len() - survived.len(),
+    }
+}
+
+// Three colour spacetime diagram of a damage run with one row per generation, written as
+// a PNG if still is set and a single frame GIF otherwise
+pub struct DamageImage<W: Write> {
+    writer: Option<W>,
+    still: bool,
+    width: u16,
+    rows: Vec<u8>,
+    height: u16,
+}
+
+impl<W: Write> DamageImage<W> {
+    pub fn new(writer: W, width: u16, still: bool) -> DamageImage<W> {
+        DamageImage {
+            writer: Some(writer),
+            still,
+            width,
+            rows: Vec::new(),
+            height: 0,
+        }
+    }
+
+    pub fn push(&mut self, generation: &DamageGeneration) {
+        self.rows.extend((0..self.width as usize).map(|i| {
+            match (generation.original.get(i), generation.perturbed.get(i)) {
+                (0, 0) => AGREE_EMPTY,
+                (1, 1) => AGREE_OCCUPIED,
+                _ => DIFFER,
+            }
+        }));
+        self.height += 1;
+    }
+
+    // Fails if no generation was pushed, as there is no image of no rows
+    pub fn finish(&mut self) -> Result<(), EncodingError> {
+        let writer = match self.writer.take() {
+            Some(writer) => writer,
+            None => return Ok(()),
+        };
+        if self.height == 0 {
+            let e = io::Error::new(io::ErrorKind::InvalidInput, "No generations were drawn");
+            return Err(e.into());
+        }
+        if !self.still {
+            let mut encoder = Encoder::new(writer, self.width, self.height, &PALETTE)?;
+            let frame = Frame::from_indexed_pixels(self.width, self.height, &self.rows, None);
+            return encoder.write_frame(&frame);
+        }
+        let mut encoder = png::Encoder::new(writer, self.width.into(), self.height.into());
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(PALETTE.to_vec());
+        let mut writer = encoder.write_header().map_err(io::Error::from)?;
+        writer
+            .write_image_data(&self.rows)
+            .map_err(io::Error::from)?;
+        Ok(())
+    }
+}
+
+// Derrida plots of several rules: the expected distance between two random states after one
+// step, against the distance between them, both as fractions of the width
+pub struct Derrida {
+    pub rules: Vec<u8>,
+    // Initial distances are 0, 1/points, ..., 1
+    pub points: usize,
+    // Random pairs of states averaged over at each initial distance
+    pub samples: usize,
+    // PNG the plots are drawn to, if any
+    pub chart: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DerridaPoint {
+    pub initial: f64,
+    pub expected: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerridaPlot {
+    pub rule: u8,
+    pub points: Vec<DerridaPoint>,
+}
+
+impl DerridaPlot {
+    // Pairs of states are a random state of the given density and a copy of it with
+    // the cells of a random subset flipped
+    pub fn measure<R: Rng>(
+        rule: u8,
+        width: u64,
+        boundary: Boundary,
+        density: f64,
+        derrida: &Derrida,
+        rng: &mut R,
+    ) -> DerridaPlot {
+        let points = derrida.points.max(1);
+        let points = (0..=points)
+            .map(|point| {
+                let flipped = (point as f64 * width as f64 / points as f64).round() as usize;
+                let mut hamming = 0;
+                for _ in 0..derrida.samples {
+                    let mut original = BitMap::random_with_rng(width, density, rng);
+                    let mut perturbed = original.clone();
+                    for cell in index::sample(rng, width as usize, flipped).into_iter() {
+                        if perturbed.get(cell) == 1 {
+                            perturbed.unset(cell);
+                        } else {
+                            perturbed.set(cell);
+                        }
+                    }
+                    original.rule_step_with_boundary(rule, boundary);
+                    perturbed.rule_step_with_boundary(rule, boundary);
+                    hamming += (0..width as usize)
+                        .filter(|&i| original.get(i) != perturbed.get(i))
+                        .count();
+                }
+                DerridaPoint {
+                    initial: flipped as f64 / width as f64,
+                    expected: hamming as f64 / (derrida.samples.max(1) as f64 * width as f64),
+                }
+            })
+            .collect();
+        DerridaPlot { rule, points }
+    }
+
+    // Slope of the plot at the origin, estimated from the smallest non-zero initial
+    // distance. Ordered rules fall below 1 and chaotic rules rise above it
+    pub fn slope(&self) -> Option<f64> {
+        self.points
+            .iter()
+            .find(|p| p.initial > 0.0)
+            .map(|p| p.expected / p.initial)
+    }
+
+    pub fn csv_header() -> &'static str {
+        "rule,initial,expected"
+    }
+
+    // One line per point
+    pub fn to_csv(&self) -> Vec<String> {
+        self.points
+            .iter()
+            .map(|p| format!("{},{},{}", self.rule, p.initial, p.expected))
+            .collect()
+    }
+}
+
+const CHART_SIZE: usize = 256;
+const CHART_MARGIN: usize = 8;
+// Grey levels of the chart
+const CHART_FRAME: u8 = 0x80;
+const CHART_DIAGONAL: u8 = 0xC0;
+const CHART_CURVE: u8 = 0x00;
+
+// Draws the plots on one set of axes running from 0 to 1, over the diagonal that
+// separates ordered from chaotic rules, as a greyscale PNG
+pub fn write_derrida_chart<W: Write>(writer: W, plots: &[DerridaPlot]) -> io::Result<()> {
+    let side = CHART_SIZE + 2 * CHART_MARGIN;
+    let mut pixels = vec![0xFF; side * side];
+    // Pixel of a point, with the y axis pointing up
+    let pixel = |x: f64, y: f64| {
+        let scale = |v: f64| (v.clamp(0.0, 1.0) * (CHART_SIZE - 1) as f64).round() as usize;
+        (
+            CHART_MARGIN + scale(x),
+            CHART_MARGIN + CHART_SIZE - 1 - scale(y),
+        )
+    };
+    let mut line = |from: (usize, usize), to: (usize, usize), shade: u8| {
+        let steps = from.0.abs_diff(to.0).max(from.1.abs_diff(to.1)).max(1);
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            let x = from.0 as f64 + (to.0 as f64 - from.0 as f64) * t;
+            let y = from.1 as f64 + (to.1 as f64 - from.1 as f64) * t;
+            pixels[y.round() as usize * side + x.round() as usize] = shade;
+        }
+    };
+    line(pixel(0.0, 0.0), pixel(1.0, 1.0), CHART_DIAGONAL);
+    let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.0, 0.0)];
+    for pair in corners.windows(2) {
+        line(
+            pixel(pair[0].0, pair[0].1),
+            pixel(pair[1].0, pair[1].1),
+            CHART_FRAME,
+        );
+    }
+    for plot in plots {
+        for pair in plot.points.windows(2) {
+            line(
+                pixel(pair[0].initial, pair[0].expected),
+                pixel(pair[1].initial, pair[1].expected),
+                CHART_CURVE,
+            );
+        }
+    }
+
+    let mut encoder = png::Encoder::new(writer, side as u32, side as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::from)?;
+    writer.write_image_data(&pixels).map_err(io::Error::from)
+}
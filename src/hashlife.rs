@@ -0,0 +1,307 @@
+use crate::image_manip::bitmap::BitMap;
+use crate::lattice::{check_generation, check_quiescent, Unbounded};
+use crate::rule::Rule;
+
+use std::collections::HashMap;
+
+type NodeId = u32;
+
+// Ids of the two single cells, the nodes of level 0
+const EMPTY_CELL: NodeId = 0;
+const OCCUPIED_CELL: NodeId = 1;
+
+// Block of 2^level cells made of two blocks of half the size, the left one holding the
+// cells of lower index. Equal blocks are stored once, so a node is known by its id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Node {
+    level: u8,
+    left: NodeId,
+    right: NodeId,
+}
+
+// Hashlife for elementary rules on an unbounded lattice of empty cells: blocks of cells
+// are stored once however often they occur, and the centre half of a block of 2^k cells
+// after up to 2^(k-2) generations is computed once and remembered. Runs with repeating
+// structure, such as gliders on the ether of rule 110, then advance 2^k generations in
+// time that grows far slower than the number of generations
+#[derive(Debug, Clone)]
+pub struct HashLife {
+    rule: Rule,
+    nodes: Vec<Node>,
+    // Number of occupied cells of each node, and the sum of their positions within it
+    population: Vec<u64>,
+    moment: Vec<f64>,
+    ids: HashMap<Node, NodeId>,
+    // Centre half of a node after 2^j generations, by node and j
+    results: HashMap<(NodeId, u8), NodeId>,
+    // Empty node of each level
+    empty: Vec<NodeId>,
+    root: NodeId,
+    // Position of the root's leftmost cell, with cell i of the initial bitmap at i
+    origin: i64,
+    generation: u64,
+}
+
+impl HashLife {
+    // Places cells at positions 0 up to their width, with empty cells everywhere else.
+    // Fails for rules that turn three empty cells occupied, which have no empty background
+    pub fn new(rule: Rule, cells: &BitMap) -> Result<HashLife, String> {
+        check_quiescent(rule)?;
+        let mut life = HashLife {
+            rule,
+            nodes: vec![
+                Node {
+                    level: 0,
+                    left: EMPTY_CELL,
+                    right: EMPTY_CELL,
+                },
+                Node {
+                    level: 0,
+                    left: OCCUPIED_CELL,
+                    right: OCCUPIED_CELL,
+                },
+            ],
+            population: vec![0, 1],
+            moment: vec![0.0, 0.0],
+            ids: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![EMPTY_CELL],
+            root: EMPTY_CELL,
+            origin: 0,
+            generation: 0,
+        };
+        // Pairs up blocks until a single one of at least 4 cells is left
+        let mut blocks: Vec<NodeId> = (0..cells.size())
+            .map(|i| {
+                if cells.get(i) == 1 {
+                    OCCUPIED_CELL
+                } else {
+                    EMPTY_CELL
+                }
+            })
+            .collect();
+        if blocks.is_empty() {
+            blocks.push(EMPTY_CELL);
+        }
+        let mut level = 0;
+        while blocks.len() > 1 || level < 2 {
+            if blocks.len() % 2 == 1 {
+                blocks.push(life.empty(level));
+            }
+            blocks = blocks
+                .chunks(2)
+                .map(|pair| life.node(level + 1, pair[0], pair[1]))
+                .collect();
+            level += 1;
+        }
+        life.root = blocks[0];
+        Ok(life)
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn population(&self) -> u64 {
+        self.population[self.root as usize]
+    }
+
+    // Number of distinct blocks stored, which is what the memory use grows with
+    pub fn nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn level(&self) -> u8 {
+        self.nodes[self.root as usize].level
+    }
+
+    fn node(&mut self, level: u8, left: NodeId, right: NodeId) -> NodeId {
+        let node = Node { level, left, right };
+        if let Some(id) = self.ids.get(&node) {
+            return *id;
+        }
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(node);
+        self.population
+            .push(self.population[left as usize] + self.population[right as usize]);
+        // Cells of the right half are half the size further along
+        let half = (1u64 << (level - 1)) as f64;
+        self.moment.push(
+            self.moment[left as usize]
+                + self.moment[right as usize]
+                + self.population[right as usize] as f64 * half,
+        );
+        self.ids.insert(node, id);
+        id
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let half = *self.empty.last().unwrap();
+            let next = self.node(self.empty.len() as u8, half, half);
+            self.empty.push(next);
+        }
+        self.empty[level as usize]
+    }
+
+    fn children(&self, id: NodeId) -> (NodeId, NodeId) {
+        let node = self.nodes[id as usize];
+        (node.left, node.right)
+    }
+
+    // Surrounds the root with empty blocks of half its size, doubling it
+    fn expand(&mut self) {
+        let level = self.level();
+        let (left, right) = self.children(self.root);
+        let empty = self.empty(level - 1);
+        let left = self.node(level, empty, left);
+        let right = self.node(level, right, empty);
+        self.root = self.node(level + 1, left, right);
+        self.origin -= 1 << (level - 1);
+    }
+
+    // Whether every occupied cell is in the centre quarter of the root
+    fn centred(&self) -> bool {
+        let (left, right) = self.children(self.root);
+        let ((outer_left, inner_left), (inner_right, outer_right)) =
+            (self.children(left), self.children(right));
+        [
+            outer_left,
+            self.children(inner_left).0,
+            self.children(inner_right).1,
+            outer_right,
+        ]
+        .iter()
+        .all(|id| self.population[*id as usize] == 0)
+    }
+
+    // Node of the cells in the middle of two nodes of the same level
+    fn centre(&mut self, left: NodeId, right: NodeId) -> NodeId {
+        let level = self.nodes[left as usize].level;
+        let (left, right) = (self.children(left).1, self.children(right).0);
+        self.node(level, left, right)
+    }
+
+    // Centre half of a node of level k after 2^j generations, for j up to k - 2
+    fn step(&mut self, id: NodeId, j: u8) -> NodeId {
+        let level = self.nodes[id as usize].level;
+        if self.population[id as usize] == 0 {
+            return self.empty(level - 1);
+        }
+        if let Some(result) = self.results.get(&(id, j)) {
+            return *result;
+        }
+        let (left, right) = self.children(id);
+        let result = if level == 2 {
+            // Pattern bit 0b100 is the cell above, as in BitMap::rule_step
+            let (a, b) = (self.children(left), self.children(right));
+            let cells = [a.0, a.1, b.0, b.1];
+            let next = |i: usize| {
+                let pattern = cells[i + 1] << 2 | cells[i] << 1 | cells[i - 1];
+                if self.rule.number() >> pattern & 1 == 1 {
+                    OCCUPIED_CELL
+                } else {
+                    EMPTY_CELL
+                }
+            };
+            let (first, second) = (next(1), next(2));
+            self.node(1, first, second)
+        } else {
+            // Three overlapping halves, each taken 2^(k-3) generations ahead or 2^j if
+            // that is less. Pairs of their results are taken the rest of the way
+            let middle = self.centre(left, right);
+            let first = j.min(level - 3);
+            let r0 = self.step(left, first);
+            let r1 = self.step(middle, first);
+            let r2 = self.step(right, first);
+            let (left, right) = (self.node(level - 1, r0, r1), self.node(level - 1, r1, r2));
+            let (s0, s1) = if j == level - 2 {
+                (self.step(left, level - 3), self.step(right, level - 3))
+            } else {
+                (self.centre(r0, r1), self.centre(r1, r2))
+            };
+            self.node(level - 1, s0, s1)
+        };
+        self.results.insert((id, j), result);
+        result
+    }
+
+    // Sets the occupied cells of node id, whose leftmost cell is at x, in cells from x0 on
+    fn fill(&self, id: NodeId, x: i64, x0: i64, cells: &mut BitMap) {
+        let node = self.nodes[id as usize];
+        let size = 1i64 << node.level;
+        let end = x0 + cells.size() as i64;
+        if self.population[id as usize] == 0 || x >= end || x + size <= x0 {
+            return;
+        }
+        if node.level == 0 {
+            cells.set((x - x0) as usize);
+            return;
+        }
+        let half = size / 2;
+        self.fill(node.left, x, x0, cells);
+        self.fill(node.right, x + half, x0, cells);
+    }
+}
+
+impl Unbounded for HashLife {
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    // Advances by steps generations, one power of 2 for each set bit of steps
+    fn advance(&mut self, steps: u64) -> Result<(), String> {
+        check_generation(self.generation, steps)?;
+        for j in 0..64 {
+            if steps >> j & 1 == 0 {
+                continue;
+            }
+            // Growing by at most 2^j cells a side, the pattern must stay inside the
+            // centre half the result covers
+            while self.level() < j + 3 || !self.centred() {
+                self.expand();
+            }
+            let level = self.level();
+            self.root = self.step(self.root, j);
+            self.origin += 1 << (level - 2);
+            self.generation += 1 << j;
+        }
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(i64, i64)> {
+        if self.population() == 0 {
+            return None;
+        }
+        let edge = |rightmost: bool| {
+            let (mut id, mut x) = (self.root, self.origin);
+            while self.nodes[id as usize].level > 0 {
+                let node = self.nodes[id as usize];
+                let left_occupied = self.population[node.left as usize] > 0;
+                if rightmost && self.population[node.right as usize] > 0 || !left_occupied {
+                    x += 1 << (node.level - 1);
+                    id = node.right;
+                } else {
+                    id = node.left;
+                }
+            }
+            x
+        };
+        Some((edge(false), edge(true)))
+    }
+
+    fn centre_of_mass(&self) -> Option<f64> {
+        match self.population() {
+            0 => None,
+            population => {
+                Some(self.origin as f64 + self.moment[self.root as usize] / population as f64)
+            }
+        }
+    }
+
+    fn row(&self, x0: i64, width: usize) -> BitMap {
+        let mut cells = BitMap::new(width as u64);
+        self.fill(self.root, self.origin, x0, &mut cells);
+        cells
+    }
+}
@@ -1,23 +1,32 @@
 pub mod bitmap;
+pub mod import;
+pub mod init;
+pub mod metadata;
+pub mod parse;
 use bitmap::BitMap;
+use metadata::Metadata;
 
 use gif::EncodingError;
-use gif::{Encoder, Frame, Repeat};
 
 use std::fs::File;
+use std::io::Write;
 use std::sync::mpsc::Sender;
 
 use crate::prog::Message;
+use crate::report::Stats;
+use crate::rule::Rule;
+use crate::simulation::{Generation, Simulation};
+use crate::sink::{drive, GifSink, PngSink, Window};
 
 enum Colour {
-    WHITE,
-    BLACK,
+    White,
+    Black,
 }
 
 fn push_pixel(vec: &mut Vec<u8>, colour: Colour) {
     vec.push(match colour {
-        Colour::WHITE => 0,
-        Colour::BLACK => 1,
+        Colour::White => 0,
+        Colour::Black => 1,
     });
 }
 
@@ -32,9 +41,9 @@ pub fn init_image(
     for _y in 0..height {
         for x in 0..width {
             if init_line.get(x.into()) == 1 {
-                push_pixel(&mut image, Colour::BLACK);
+                push_pixel(&mut image, Colour::Black);
             } else {
-                push_pixel(&mut image, Colour::WHITE);
+                push_pixel(&mut image, Colour::White);
             }
         }
         init_line.rule_step(rule);
@@ -58,8 +67,8 @@ pub fn gen_next_image(
     line.rule_step(rule);
     for x in 0..width {
         match line.get(x.into()) {
-            1 => push_pixel(image, Colour::BLACK),
-            0 => push_pixel(image, Colour::WHITE),
+            1 => push_pixel(image, Colour::Black),
+            0 => push_pixel(image, Colour::White),
             _ => panic!(),
         }
     }
@@ -68,11 +77,6 @@ pub fn gen_next_image(
     Ok(())
 }
 
-fn build_frame(width: u16, height: u16, img: &[u8]) -> Frame {
-    let frame = Frame::from_indexed_pixels(width, height, img, None);
-    frame
-}
-
 pub fn build_gif(
     width: u16,
     height: u16,
@@ -82,38 +86,136 @@ pub fn build_gif(
     progress_bar_tx_wrap: Option<&Sender<Message>>,
     rule: u8,
 ) -> Result<(), EncodingError> {
-    let mut file = File::create(file_name)?;
-    // Set with two colours: white, black
-    let color_map = &[0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00];
-    let mut encoder = Encoder::new(&mut file, width, height, color_map)?;
-    encoder.set_repeat(Repeat::Infinite)?;
-    // build initial frame
-    let mut img = init_image(width, height, init_line, rule)?;
-    let frame = build_frame(width, height, &img);
-
-    encoder.write_frame(&frame)?;
-
-    match progress_bar_tx_wrap {
-        Some(progress_bar_tx) => {
-            // iterate over other frames
-            for s in 1..steps {
-                gen_next_image(&mut img, width, height, init_line, rule)?;
-                let frame = build_frame(width, height, &img);
-                encoder.write_frame(&frame)?;
-                // Update progress bar
-                progress_bar_tx.send(Message::Update(s)).unwrap();
-            }
-            // Finish updating progress bar
-            progress_bar_tx.send(Message::Kill).unwrap();
-        }
-        None => {
-            for _ in 1..steps {
-                gen_next_image(&mut img, width, height, init_line, rule)?;
-                let frame = build_frame(width, height, &img);
-                encoder.write_frame(&frame)?;
-            }
-        }
-    }
+    let file = File::create(file_name)?;
+    build_gif_to_writer(
+        width,
+        height,
+        steps,
+        init_line,
+        file,
+        progress_bar_tx_wrap,
+        rule,
+    )
+}
 
+// Same as build_gif, writing the GIF to writer instead of a file, e.g. to stdout
+pub fn build_gif_to_writer<W: Write>(
+    width: u16,
+    height: u16,
+    steps: u32,
+    init_line: &mut BitMap,
+    writer: W,
+    progress_bar_tx_wrap: Option<&Sender<Message>>,
+    rule: u8,
+) -> Result<(), EncodingError> {
+    let metadata = Metadata::new(rule, width, height, steps, init_line);
+    let mut simulation = metadata.simulation();
+    render_gif_to_writer(simulation.by_ref(), &metadata, writer, progress_bar_tx_wrap)?;
+    // Leaves init_line one generation past the last one drawn, as it always has
+    simulation.step();
+    *init_line = simulation.cells().clone();
     Ok(())
 }
+
+// Same as build_gif, taking the dimensions and rule from metadata, which is written to
+// the GIF as a comment, and returning timings and statistics of the run
+pub fn build_gif_with_metadata(
+    metadata: &Metadata,
+    init_line: &mut BitMap,
+    file_name: &str,
+    progress_bar_tx_wrap: Option<&Sender<Message>>,
+) -> Result<Stats, EncodingError> {
+    let mut simulation = Simulation::new(
+        Rule::new(metadata.rule),
+        metadata.boundary,
+        init_line.clone(),
+    );
+    let stats = render_gif(
+        simulation.by_ref(),
+        metadata,
+        file_name,
+        progress_bar_tx_wrap,
+    )?;
+    simulation.step();
+    *init_line = simulation.cells().clone();
+    Ok(stats)
+}
+
+// Draws generations as an animation whose frames are a window of metadata.height
+// generations, moving down by one generation per frame. Stops early if generations
+// runs out
+pub fn render_gif<I: Iterator<Item = Generation>>(
+    generations: I,
+    metadata: &Metadata,
+    file_name: &str,
+    progress_bar_tx_wrap: Option<&Sender<Message>>,
+) -> Result<Stats, EncodingError> {
+    let file = File::create(file_name)?;
+    render_gif_to_writer(generations, metadata, file, progress_bar_tx_wrap)
+}
+
+pub fn render_gif_to_writer<I: Iterator<Item = Generation>, W: Write>(
+    generations: I,
+    metadata: &Metadata,
+    writer: W,
+    progress_bar_tx_wrap: Option<&Sender<Message>>,
+) -> Result<Stats, EncodingError> {
+    let mut sink = GifSink::animation(writer, metadata)?;
+    let count = Window::<GifSink<W>>::generations_for(metadata.height, metadata.frames);
+    drive(
+        generations.take(count as usize),
+        &mut sink,
+        progress_bar_tx_wrap,
+    )
+}
+
+// Same as render_gif, except that outputs ending in .png are a still image of the first
+// metadata.height generations
+pub fn render_file<I: Iterator<Item = Generation>>(
+    generations: I,
+    metadata: &Metadata,
+    file_name: &str,
+    progress_bar_tx_wrap: Option<&Sender<Message>>,
+) -> Result<Stats, EncodingError> {
+    let file = File::create(file_name)?;
+    render_to_writer(
+        generations,
+        metadata,
+        file,
+        is_still(file_name),
+        progress_bar_tx_wrap,
+    )
+}
+
+// Writes a still image if still is set, as render_file does for .png outputs, and an
+// animation otherwise
+pub fn render_to_writer<I: Iterator<Item = Generation>, W: Write>(
+    generations: I,
+    metadata: &Metadata,
+    writer: W,
+    still: bool,
+    progress_bar_tx_wrap: Option<&Sender<Message>>,
+) -> Result<Stats, EncodingError> {
+    if !still {
+        return render_gif_to_writer(generations, metadata, writer, progress_bar_tx_wrap);
+    }
+    let mut sink = PngSink::new(writer, metadata);
+    drive(
+        generations.take(metadata.height.into()),
+        &mut sink,
+        progress_bar_tx_wrap,
+    )
+}
+
+pub fn is_still(file_name: &str) -> bool {
+    file_name.to_lowercase().ends_with(".png")
+}
+
+// Number of generations render_file draws, for sizing progress bars
+pub fn generations_drawn(metadata: &Metadata, file_name: &str) -> u64 {
+    if is_still(file_name) {
+        metadata.height.into()
+    } else {
+        Window::<GifSink<File>>::generations_for(metadata.height, metadata.frames)
+    }
+}
@@ -1,8 +1,74 @@
+use crate::rule::Rule;
+
 use bit_vec::BitVec;
 use rand::distributions::{Bernoulli, Distribution};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::convert::TryInto;
+use std::fmt;
+use std::str::FromStr;
+
+// Spatial period of the rule 110 ether, left neighbour first
+const ETHER: &[u8] = b"11111000100110";
 
-#[derive(Debug)]
+// Rule 110 gliders that can be placed in the ether
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Glider {
+    // Moves 2 cells every 3 generations
+    A,
+    // Moves 2 cells every 4 generations, in the opposite direction to A
+    B,
+}
+
+impl Glider {
+    // Cells replacing part of the ether, left neighbour first
+    pub fn pattern(&self) -> &'static [u8] {
+        match self {
+            Glider::A => b"111110",
+            Glider::B => b"11111010",
+        }
+    }
+}
+
+// What the cells beyond either edge of the lattice are taken to be
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Boundary {
+    // The edges neighbour each other, as on a ring
+    Periodic,
+    // Cells beyond the edges are always in the given state
+    Fixed(bool),
+    // Cells beyond the edges copy the edge cells
+    Reflect,
+}
+
+impl FromStr for Boundary {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Boundary, String> {
+        match spec.trim() {
+            "periodic" => Ok(Boundary::Periodic),
+            "fixed" | "fixed:0" => Ok(Boundary::Fixed(false)),
+            "fixed:1" => Ok(Boundary::Fixed(true)),
+            "reflect" => Ok(Boundary::Reflect),
+            _ => Err(format!(
+                "Unknown boundary '{}', expected periodic, fixed:0, fixed:1 or reflect",
+                spec
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Boundary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Boundary::Periodic => write!(f, "periodic"),
+            Boundary::Fixed(state) => write!(f, "fixed:{}", *state as u8),
+            Boundary::Reflect => write!(f, "reflect"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct BitMap {
     bit_vector: Box<[BitVec; 2]>,
     current_index: bool,
@@ -28,13 +94,26 @@ impl BitMap {
     }
 
     pub fn random(length: u64, density: f64) -> BitMap {
+        BitMap::random_with_rng(length, density, &mut rand::thread_rng())
+    }
+
+    // Same as random, but reproducible: equal seeds always produce equal bitmaps
+    pub fn random_seeded(length: u64, density: f64, seed: u64) -> BitMap {
+        BitMap::random_with_rng(length, density, &mut StdRng::seed_from_u64(seed))
+    }
+
+    pub fn random_with_rng<R: Rng>(length: u64, density: f64, rng: &mut R) -> BitMap {
         let d = Bernoulli::new(density).unwrap();
+        BitMap::from_fn(length, |_| d.sample(rng))
+    }
+
+    // Bitmap whose bit at each index is given by f
+    pub fn from_fn<F: FnMut(usize) -> bool>(length: u64, f: F) -> BitMap {
         if length == 0 {
             panic!("Cannot create 0-length bit-map");
         }
-        let mut rng = rand::thread_rng();
         let bit_vector = [
-            BitVec::from_fn(length.try_into().unwrap(), |_| d.sample(&mut rng)),
+            BitVec::from_fn(length.try_into().unwrap(), f),
             BitVec::from_elem(length.try_into().unwrap(), false),
         ];
 
@@ -45,6 +124,110 @@ impl BitMap {
         }
     }
 
+    // Single occupied cell in the middle of an empty bitmap
+    pub fn single(length: u64) -> BitMap {
+        BitMap::single_at(length, (length / 2) as usize)
+    }
+
+    // Single occupied cell at index, panics if index is out of range
+    pub fn single_at(length: u64, index: usize) -> BitMap {
+        let mut bitmap = BitMap::new(length);
+        bitmap.set(index);
+        bitmap
+    }
+
+    // pattern repeated from index 0 onwards, truncated at the end of the bitmap
+    pub fn periodic(length: u64, pattern: &[bool]) -> BitMap {
+        if pattern.is_empty() {
+            panic!("Cannot tile an empty pattern");
+        }
+        BitMap::from_fn(length, |i| pattern[i % pattern.len()])
+    }
+
+    // count evenly spaced blocks of block_len random cells with the given density,
+    // in an otherwise empty bitmap
+    pub fn random_blocks<R: Rng>(
+        length: u64,
+        count: usize,
+        block_len: usize,
+        density: f64,
+        rng: &mut R,
+    ) -> BitMap {
+        let len = length as usize;
+        if count == 0 || block_len > len || count.saturating_mul(block_len) > len {
+            panic!(
+                "{} blocks of {} cells do not fit in {} cells",
+                count, block_len, len
+            );
+        }
+        let d = Bernoulli::new(density).unwrap();
+        let spacing = len / count;
+        BitMap::from_fn(length, |i| {
+            // Each block sits in the middle of its share of the bitmap
+            let start = (i / spacing) * spacing + (spacing - block_len) / 2;
+            i / spacing < count && i >= start && i < start + block_len && d.sample(rng)
+        })
+    }
+
+    // Two state Markov chain along the bitmap: an empty cell is followed by an occupied
+    // one with probability p_birth and an occupied cell by an empty one with probability
+    // p_death, giving runs correlated over roughly 1 / (p_birth + p_death) cells
+    pub fn markov<R: Rng>(length: u64, p_birth: f64, p_death: f64, rng: &mut R) -> BitMap {
+        let birth = Bernoulli::new(p_birth).unwrap();
+        let death = Bernoulli::new(p_death).unwrap();
+        // Start from the stationary distribution so that no end of the bitmap is special
+        let stationary = if p_birth + p_death > 0.0 {
+            p_birth / (p_birth + p_death)
+        } else {
+            0.5
+        };
+        let mut state = Bernoulli::new(stationary).unwrap().sample(rng);
+        BitMap::from_fn(length, |i| {
+            if i > 0 {
+                state = if state {
+                    !death.sample(rng)
+                } else {
+                    birth.sample(rng)
+                };
+            }
+            state
+        })
+    }
+
+    // Periodic rule 110 background with the given gliders spread evenly across it. The
+    // ether tiles cleanly when length is 14 times some n plus the lengths of the gliders
+    pub fn ether(length: u64, gliders: &[Glider]) -> BitMap {
+        let len = length as usize;
+        let glider_len: usize = gliders.iter().map(|g| g.pattern().len()).sum();
+        if glider_len > len {
+            panic!(
+                "Gliders need {} cells but only {} are available",
+                glider_len, len
+            );
+        }
+        let tiles = (len - glider_len) / ETHER.len();
+        let slots = gliders.len().max(1);
+        let mut cells: Vec<bool> = Vec::with_capacity(len);
+        for slot in 0..slots {
+            let slot_tiles = tiles * (slot + 1) / slots - tiles * slot / slots;
+            for _ in 0..slot_tiles {
+                cells.extend(ETHER.iter().map(|c| *c == b'1'));
+            }
+            if let Some(glider) = gliders.get(slot) {
+                cells.extend(glider.pattern().iter().map(|c| *c == b'1'));
+            }
+        }
+        // Whatever is left over is filled with a partial tile
+        let mut i = 0;
+        while cells.len() < len {
+            cells.push(ETHER[i % ETHER.len()] == b'1');
+            i += 1;
+        }
+        // The patterns are written with the left neighbour first, BitMap indices run the
+        // other way (see rule_step)
+        BitMap::from_fn(length, |i| cells[len - 1 - i])
+    }
+
     // if bit_num is less than len, then return bit at that position, otherwise panic
     pub fn get(&self, bit_num: usize) -> u8 {
         let bit_vector = &self.bit_vector[self.current_index as usize];
@@ -99,12 +282,29 @@ impl BitMap {
         bit_vec
     }
 
+    // Cells as 1s and 0s, the inverse of from_bit_string
+    pub fn to_bit_string(&self) -> String {
+        (0..self.len)
+            .map(|i| if self.get(i) == 1 { '1' } else { '0' })
+            .collect()
+    }
+
+    // Any character other than '1' is an empty cell
+    pub fn from_bit_string(bits: &str) -> BitMap {
+        let cells: Vec<bool> = bits.chars().map(|c| c == '1').collect();
+        BitMap::from_fn(cells.len() as u64, |i| cells[i])
+    }
+
     pub fn clear(&mut self) {
         let bit_vector = &mut self.bit_vector[self.current_index as usize];
         bit_vector.clear();
     }
 
     pub fn rule_step(&mut self, rule: u8) {
+        self.rule_step_with_boundary(rule, Boundary::Periodic);
+    }
+
+    pub fn rule_step_with_boundary(&mut self, rule: u8, boundary: Boundary) {
         self.bit_vector[!self.current_index as usize].clear();
         enum Offset {
             PlusOne,
@@ -112,21 +312,34 @@ impl BitMap {
             MinusOne,
         }
         let len = self.size();
+        // State of the cell beyond the edge cell at index edge, where wrapped is the edge
+        // cell on the opposite side of the lattice
+        let outside = |bitmap: &BitMap, edge: usize, wrapped: usize| match boundary {
+            Boundary::Periodic => bitmap.get(wrapped) == 1,
+            Boundary::Fixed(state) => state,
+            Boundary::Reflect => bitmap.get(edge) == 1,
+        };
         for i in 0..len {
             let mut flags: u8 = 0;
             for offset in [Offset::PlusOne, Offset::Zero, Offset::MinusOne].iter() {
-                let (flag_mask, index) = match offset {
-                    Offset::PlusOne => (0b100, (i + 1) % self.size()),
-                    Offset::Zero => (0b010, i),
+                let (flag_mask, occupied) = match offset {
+                    Offset::PlusOne => {
+                        if i + 1 == len {
+                            (0b100, outside(self, i, 0))
+                        } else {
+                            (0b100, self.get(i + 1) == 1)
+                        }
+                    }
+                    Offset::Zero => (0b010, self.get(i) == 1),
                     Offset::MinusOne => {
                         if i == 0 {
-                            (0b001, len - 1)
+                            (0b001, outside(self, i, len - 1))
                         } else {
-                            (0b001, i - 1)
+                            (0b001, self.get(i - 1) == 1)
                         }
                     }
                 };
-                if self.get(index) == 1 {
+                if occupied {
                     flags |= flag_mask;
                 }
             }
@@ -139,4 +352,39 @@ impl BitMap {
         }
         self.current_index = !self.current_index;
     }
+
+    // Advances an additive rule such as 90 or 150 by steps generations on a periodic
+    // lattice in O(n log steps). Over GF(2) one step multiplies the lattice by
+    // l x + c + r x^-1, whose 2^k-th power is l x^(2^k) + c + r x^-(2^k), so each set
+    // bit of steps costs one pass over the cells
+    pub fn additive_jump(&mut self, rule: u8, steps: u64) -> Result<(), String> {
+        if !Rule::new(rule).properties().additive {
+            return Err(format!("Rule {} is not additive", rule));
+        }
+        let len = self.size();
+        if len == 0 {
+            return Ok(());
+        }
+        let (left, centre, right) = (rule & 0b10000 != 0, rule & 0b100 != 0, rule & 0b10 != 0);
+        let mut cells = self.get_vec();
+        // 2^k mod len, the distance the cells are combined over for bit k
+        let mut distance = 1 % len;
+        for k in 0..64 {
+            if steps >> k & 1 == 1 {
+                cells = (0..len)
+                    .map(|i| {
+                        (left && cells[(i + distance) % len])
+                            ^ (centre && cells[i])
+                            ^ (right && cells[(i + len - distance) % len])
+                    })
+                    .collect();
+            }
+            distance = distance * 2 % len;
+        }
+        let bit_vector = &mut self.bit_vector[self.current_index as usize];
+        for (i, cell) in cells.into_iter().enumerate() {
+            bit_vector.set(i, cell);
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,241 @@
+use super::bitmap::BitMap;
+
+use gif_dispose::Screen;
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+// Pixels darker than the threshold become occupied cells, matching the black cells of
+// the images built by this crate
+pub const DEFAULT_THRESHOLD: u8 = 128;
+
+// Which part of an image to read a row of cells from. Negative indices count back
+// from the end, so -1 is the last row or frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageRow {
+    pub path: String,
+    pub row: i64,
+    // Only used for GIFs
+    pub frame: i64,
+    pub threshold: u8,
+    // Number of pixels of the row that are cells, if not all of them, e.g. when a
+    // statistics strip is drawn next to the cells
+    pub columns: Option<usize>,
+}
+
+impl ImageRow {
+    pub fn new(path: &str) -> ImageRow {
+        ImageRow {
+            path: String::from(path),
+            row: 0,
+            frame: 0,
+            threshold: DEFAULT_THRESHOLD,
+            columns: None,
+        }
+    }
+
+    // Last row of the last frame, where an animation built by build_gif left off
+    pub fn last_of(path: &str) -> ImageRow {
+        ImageRow {
+            row: -1,
+            frame: -1,
+            ..ImageRow::new(path)
+        }
+    }
+
+    // Reads the row, thresholds it and stretches or shrinks it to width cells if given
+    pub fn to_bitmap(&self, width: Option<u16>) -> Result<BitMap, String> {
+        let mut data = Vec::new();
+        File::open(&self.path)
+            .and_then(|mut f| f.read_to_end(&mut data))
+            .map_err(|e| format!("Could not read {}: {}", self.path, e))?;
+
+        let image = if data.starts_with(b"GIF8") {
+            decode_gif(&data, self.frame)?
+        } else if data.starts_with(b"\x89PNG") {
+            decode_png(&data)?
+        } else if data.starts_with(b"P1") || data.starts_with(b"P4") {
+            decode_pbm(&data)?
+        } else {
+            return Err(format!("{} is not a GIF, PNG or PBM image", self.path));
+        };
+
+        if image.width == 0 || width == Some(0) || self.columns == Some(0) {
+            return Err(String::from("Cannot read a row of no cells"));
+        }
+        let row = resolve_index(self.row, image.height, "Row")?;
+        let columns = self.columns.unwrap_or(image.width).min(image.width);
+        let luminance = &image.pixels[row * image.width..row * image.width + columns];
+        let width = match width {
+            Some(w) => w as usize,
+            None if columns <= u16::MAX as usize => columns,
+            None => {
+                return Err(format!(
+                    "Image is {} pixels wide but can be at most {}",
+                    columns,
+                    u16::MAX
+                ))
+            }
+        };
+        // Nearest neighbour resampling
+        Ok(BitMap::from_fn(width as u64, |x| {
+            luminance[x * columns / width] < self.threshold
+        }))
+    }
+}
+
+// Greyscale image, one byte of luminance per pixel
+struct Luminance {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+fn resolve_index(index: i64, len: usize, name: &str) -> Result<usize, String> {
+    let resolved = if index < 0 { len as i64 + index } else { index };
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(format!(
+            "{} {} is outside an image with {} of them",
+            name, index, len
+        ));
+    }
+    Ok(resolved as usize)
+}
+
+// Luminance of a colour drawn over a white background
+fn luminance(r: u8, g: u8, b: u8, a: u8) -> u8 {
+    let lum = (299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000;
+    ((lum * a as u32 + 255 * (255 - a as u32)) / 255) as u8
+}
+
+fn decode_gif(data: &[u8], frame: i64) -> Result<Luminance, String> {
+    let gif_error = |e: gif::DecodingError| format!("Could not decode GIF: {}", e);
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+
+    // Count frames first so that negative indices can be resolved
+    let mut decoder = options.clone().read_info(data).map_err(gif_error)?;
+    let mut num_frames = 0;
+    while decoder.next_frame_info().map_err(gif_error)?.is_some() {
+        num_frames += 1;
+    }
+    let frame = resolve_index(frame, num_frames, "Frame")?;
+
+    let mut decoder = options.read_info(data).map_err(gif_error)?;
+    let mut screen = Screen::new_decoder(&decoder);
+    for _ in 0..frame {
+        let f = decoder.read_next_frame().map_err(gif_error)?.unwrap();
+        screen.blit_frame(f).map_err(|e| e.to_string())?;
+    }
+    let f = decoder.read_next_frame().map_err(gif_error)?.unwrap();
+    let pixels = screen.blit_frame(f).map_err(|e| e.to_string())?;
+    Ok(Luminance {
+        width: pixels.width(),
+        height: pixels.height(),
+        pixels: pixels
+            .pixels()
+            .map(|p| luminance(p.r, p.g, p.b, p.a))
+            .collect(),
+    })
+}
+
+fn decode_png(data: &[u8]) -> Result<Luminance, String> {
+    let png_error = |e: png::DecodingError| format!("Could not decode PNG: {}", e);
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let (info, mut reader) = decoder.read_info().map_err(png_error)?;
+    let mut buf = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buf).map_err(png_error)?;
+
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::RGB => 3,
+        png::ColorType::RGBA => 4,
+        png::ColorType::Indexed => return Err(String::from("Could not expand PNG palette")),
+    };
+    let (width, height) = (info.width as usize, info.height as usize);
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in buf.chunks(info.line_size).take(height) {
+        for p in row.chunks(channels).take(width) {
+            pixels.push(match channels {
+                1 => p[0],
+                2 => luminance(p[0], p[0], p[0], p[1]),
+                3 => luminance(p[0], p[1], p[2], 255),
+                _ => luminance(p[0], p[1], p[2], p[3]),
+            });
+        }
+    }
+    Ok(Luminance {
+        width,
+        height,
+        pixels,
+    })
+}
+
+// Plain (P1) and raw (P4) portable bitmaps, where 1 is black
+fn decode_pbm(data: &[u8]) -> Result<Luminance, String> {
+    let raw = data.starts_with(b"P4");
+    let mut reader = BufReader::new(&data[2..]);
+    let mut header = Vec::new();
+    // Width and height, skipping whitespace and comments
+    let mut bytes = reader.by_ref().bytes();
+    let mut token = String::new();
+    while header.len() < 2 {
+        let byte = match bytes.next() {
+            Some(Ok(b)) => b,
+            _ => return Err(String::from("PBM header ends early")),
+        };
+        if byte == b'#' {
+            for b in bytes.by_ref() {
+                if matches!(b, Ok(b'\n')) {
+                    break;
+                }
+            }
+        } else if byte.is_ascii_whitespace() {
+            if !token.is_empty() {
+                header.push(token.parse::<usize>().map_err(|_| "Invalid PBM size")?);
+                token.clear();
+            }
+        } else {
+            token.push(byte as char);
+        }
+    }
+    let (width, height) = (header[0], header[1]);
+
+    let mut pixels = Vec::with_capacity(width * height);
+    if raw {
+        // Each row is padded to a whole byte
+        let row_bytes = width / 8 + usize::from(width % 8 != 0);
+        let mut buf = vec![0; row_bytes * height];
+        reader
+            .read_exact(&mut buf)
+            .map_err(|_| String::from("PBM data ends early"))?;
+        for row in buf.chunks(row_bytes) {
+            for x in 0..width {
+                let black = row[x / 8] & (0x80 >> (x % 8)) != 0;
+                pixels.push(if black { 0 } else { 255 });
+            }
+        }
+    } else {
+        for byte in bytes {
+            match byte {
+                Ok(b'1') => pixels.push(0),
+                Ok(b'0') => pixels.push(255),
+                Ok(b) if b.is_ascii_whitespace() => {}
+                _ => return Err(String::from("PBM data must be 1s and 0s")),
+            }
+            if pixels.len() == width * height {
+                break;
+            }
+        }
+        if pixels.len() < width * height {
+            return Err(String::from("PBM data ends early"));
+        }
+    }
+    Ok(Luminance {
+        width,
+        height,
+        pixels,
+    })
+}
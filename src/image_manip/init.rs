@@ -0,0 +1,181 @@
+use super::bitmap::{BitMap, Glider};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::str::FromStr;
+
+// Named generator for the initial bitmap, as given to --init
+#[derive(Debug, Clone, PartialEq)]
+pub enum Init {
+    // random[:density]
+    Random(f64),
+    // single[:index], centred when no index is given
+    Single(Option<usize>),
+    // periodic:<pattern of 1s and 0s>
+    Periodic(Vec<bool>),
+    // blocks:<count>:<width>[:density]
+    Blocks {
+        count: usize,
+        width: usize,
+        density: f64,
+    },
+    // markov:<p_birth>:<p_death>
+    Markov {
+        p_birth: f64,
+        p_death: f64,
+    },
+    // ether[:<glider>,<glider>,...]
+    Ether(Vec<Glider>),
+}
+
+impl Init {
+    // Builds a bitmap of the given length. Random generators are seeded with seed if
+    // given, so that equal seeds give equal bitmaps
+    pub fn build(&self, length: u64, seed: Option<u64>) -> Result<BitMap, String> {
+        match seed {
+            Some(seed) => self.build_with_rng(length, &mut StdRng::seed_from_u64(seed)),
+            None => self.build_with_rng(length, &mut rand::thread_rng()),
+        }
+    }
+
+    fn build_with_rng<R: Rng>(&self, length: u64, rng: &mut R) -> Result<BitMap, String> {
+        let len = length as usize;
+        match self {
+            Init::Random(density) => Ok(BitMap::random_with_rng(length, *density, rng)),
+            Init::Single(None) => Ok(BitMap::single(length)),
+            Init::Single(Some(index)) => {
+                if *index >= len {
+                    return Err(format!("Cell {} is outside a width of {}", index, len));
+                }
+                Ok(BitMap::single_at(length, *index))
+            }
+            Init::Periodic(pattern) => Ok(BitMap::periodic(length, pattern)),
+            Init::Blocks {
+                count,
+                width,
+                density,
+            } => {
+                if *count == 0 {
+                    return Err(String::from("Block count must be at least 1"));
+                }
+                // Each block needs a share of the bitmap at least as wide as itself
+                if *width > len || count.saturating_mul(*width) > len {
+                    return Err(format!(
+                        "{} blocks of {} cells do not fit in a width of {}",
+                        count, width, len
+                    ));
+                }
+                Ok(BitMap::random_blocks(length, *count, *width, *density, rng))
+            }
+            Init::Markov { p_birth, p_death } => {
+                Ok(BitMap::markov(length, *p_birth, *p_death, rng))
+            }
+            Init::Ether(gliders) => {
+                let glider_len: usize = gliders.iter().map(|g| g.pattern().len()).sum();
+                if glider_len > len {
+                    return Err(format!("Gliders need a width of at least {}", glider_len));
+                }
+                Ok(BitMap::ether(length, gliders))
+            }
+        }
+    }
+}
+
+fn parse_probability(value: &str, name: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(p) if (0.0..=1.0).contains(&p) => Ok(p),
+        _ => Err(format!("{} must be a number between 0.0 and 1.0", name)),
+    }
+}
+
+fn parse_count(value: &str, name: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} must be a non-negative integer", name))
+}
+
+impl FromStr for Init {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Init, String> {
+        let parts: Vec<&str> = spec.trim().split(':').collect();
+        match parts.as_slice() {
+            ["random"] => Ok(Init::Random(0.5)),
+            ["random", density] => Ok(Init::Random(parse_probability(density, "Density")?)),
+            ["single"] => Ok(Init::Single(None)),
+            ["single", index] => Ok(Init::Single(Some(parse_count(index, "Index")?))),
+            ["periodic", pattern] => {
+                if pattern.is_empty() || !pattern.chars().all(|c| c == '0' || c == '1') {
+                    return Err(String::from("Pattern must be a sequence of 1s and 0s"));
+                }
+                Ok(Init::Periodic(pattern.chars().map(|c| c == '1').collect()))
+            }
+            ["blocks", count, width] | ["blocks", count, width, _] => Ok(Init::Blocks {
+                count: match parse_count(count, "Block count")? {
+                    0 => return Err(String::from("Block count must be at least 1")),
+                    count => count,
+                },
+                width: parse_count(width, "Block width")?,
+                density: match parts.get(3) {
+                    Some(density) => parse_probability(density, "Density")?,
+                    None => 0.5,
+                },
+            }),
+            ["markov", p_birth, p_death] => Ok(Init::Markov {
+                p_birth: parse_probability(p_birth, "Birth probability")?,
+                p_death: parse_probability(p_death, "Death probability")?,
+            }),
+            ["ether"] => Ok(Init::Ether(Vec::new())),
+            ["ether", gliders] => {
+                let gliders = gliders
+                    .split(',')
+                    .map(|g| match g.trim() {
+                        "A" | "a" => Ok(Glider::A),
+                        "B" | "b" => Ok(Glider::B),
+                        other => Err(format!("Unknown glider '{}', expected A or B", other)),
+                    })
+                    .collect::<Result<Vec<Glider>, String>>()?;
+                Ok(Init::Ether(gliders))
+            }
+            _ => Err(format!(
+                "Could not interpret '{}'. Expected one of random[:density], single[:index], \
+                 periodic:<pattern>, blocks:<count>:<width>[:density], markov:<p_birth>:<p_death> \
+                 or ether[:<gliders>]",
+                spec
+            )),
+        }
+    }
+}
+
+// Writes the spec that FromStr reads back into the same generator
+impl fmt::Display for Init {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Init::Random(density) => write!(f, "random:{}", density),
+            Init::Single(None) => write!(f, "single"),
+            Init::Single(Some(index)) => write!(f, "single:{}", index),
+            Init::Periodic(pattern) => {
+                let pattern: String = pattern.iter().map(|b| if *b { '1' } else { '0' }).collect();
+                write!(f, "periodic:{}", pattern)
+            }
+            Init::Blocks {
+                count,
+                width,
+                density,
+            } => write!(f, "blocks:{}:{}:{}", count, width, density),
+            Init::Markov { p_birth, p_death } => write!(f, "markov:{}:{}", p_birth, p_death),
+            Init::Ether(gliders) if gliders.is_empty() => write!(f, "ether"),
+            Init::Ether(gliders) => {
+                let gliders: Vec<&str> = gliders
+                    .iter()
+                    .map(|g| match g {
+                        Glider::A => "A",
+                        Glider::B => "B",
+                    })
+                    .collect();
+                write!(f, "ether:{}", gliders.join(","))
+            }
+        }
+    }
+}
@@ -0,0 +1,421 @@
+use super::bitmap::{BitMap, Boundary};
+use crate::analysis::StatsStrip;
+use crate::hashlife::HashLife;
+use crate::lattice::{check_generation, GrowingLattice, Lattice, Unbounded};
+use crate::rule::Rule;
+use crate::simulation::{Generation, Simulation};
+use crate::viewport::{Cropped, Track, Viewport};
+
+use std::fs::File;
+use std::io::Read;
+
+// First word of the comment, so that comments from other tools are ignored
+const MAGIC: &str = "cellular";
+// GIF extension introducer and the label of comment extensions
+const EXTENSION: u8 = 0x21;
+pub const COMMENT_LABEL: u8 = 0xFE;
+const IMAGE: u8 = 0x2C;
+const TRAILER: u8 = 0x3B;
+// Keyword of the PNG text chunk holding the same comment
+pub const PNG_TEXT_KEYWORD: &str = "Comment";
+
+// Record of how an animation was made, stored as a comment in the GIF as lines of
+// key=value pairs following a "cellular <version>" line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    pub rule: u8,
+    pub width: u16,
+    pub height: u16,
+    pub frames: u32,
+    pub seed: Option<u64>,
+    pub density: Option<f64>,
+    // --init spec the bitmap was generated from, if any
+    pub init: Option<String>,
+    // Initial bitmap as 1s and 0s, which reproduces the run whatever generated it
+    pub bitmap: String,
+    pub boundary: Boundary,
+    // Generation of the first frame, reached from the initial bitmap
+    pub start: u64,
+    // Number of generations between consecutive rows
+    pub stride: u64,
+    pub lattice: Lattice,
+    // Position of the leftmost cell drawn at the first frame, relative to the first cell
+    // of the initial bitmap, which may be wider than the frames, and how it moves
+    pub offset: i64,
+    pub track: Track,
+    // Block size of the statistics strip drawn next to each generation, if any
+    pub strip: Option<usize>,
+    pub version: String,
+}
+
+impl Metadata {
+    pub fn new(rule: u8, width: u16, height: u16, frames: u32, init_line: &BitMap) -> Metadata {
+        Metadata {
+            rule,
+            width,
+            height,
+            frames,
+            seed: None,
+            density: None,
+            init: None,
+            bitmap: init_line.to_bit_string(),
+            boundary: Boundary::Periodic,
+            start: 0,
+            stride: 1,
+            lattice: Lattice::Finite,
+            offset: 0,
+            track: Track::Fixed,
+            strip: None,
+            version: String::from(env!("CARGO_PKG_VERSION")),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let mut lines = vec![
+            format!("{} {}", MAGIC, self.version),
+            format!("rule={}", self.rule),
+            format!("width={}", self.width),
+            format!("height={}", self.height),
+            format!("frames={}", self.frames),
+        ];
+        if let Some(seed) = self.seed {
+            lines.push(format!("seed={}", seed));
+        }
+        if let Some(density) = self.density {
+            lines.push(format!("density={}", density));
+        }
+        if let Some(init) = &self.init {
+            lines.push(format!("init={}", init));
+        }
+        lines.push(format!("boundary={}", self.boundary));
+        if self.start > 0 {
+            lines.push(format!("start={}", self.start));
+        }
+        if self.stride > 1 {
+            lines.push(format!("stride={}", self.stride));
+        }
+        if self.lattice != Lattice::Finite {
+            lines.push(format!("lattice={}", self.lattice));
+        }
+        if self.offset != 0 {
+            lines.push(format!("offset={}", self.offset));
+        }
+        if self.track != Track::Fixed {
+            lines.push(format!("track={}", self.track));
+        }
+        if let Some(k) = self.strip {
+            lines.push(format!("strip={}", k));
+        }
+        lines.push(format!("bitmap={}", self.bitmap));
+        lines.join("\n")
+    }
+
+    // Inverse of encode. Unknown keys are skipped so that files written by later
+    // versions can still be read
+    pub fn decode(comment: &str) -> Result<Metadata, String> {
+        let mut lines = comment.lines();
+        let version = match lines.next().and_then(|l| l.strip_prefix(MAGIC)) {
+            Some(version) => version.trim().to_string(),
+            None => return Err(String::from("Comment was not written by cellular")),
+        };
+        let mut metadata = Metadata {
+            rule: 0,
+            width: 0,
+            height: 0,
+            frames: 0,
+            seed: None,
+            density: None,
+            init: None,
+            bitmap: String::new(),
+            boundary: Boundary::Periodic,
+            start: 0,
+            stride: 1,
+            lattice: Lattice::Finite,
+            offset: 0,
+            track: Track::Fixed,
+            strip: None,
+            version,
+        };
+        let mut seen = Vec::new();
+        for line in lines {
+            let (key, value) = match line.split_once('=') {
+                Some(pair) => pair,
+                None => return Err(format!("Malformed metadata line '{}'", line)),
+            };
+            let invalid = || format!("Invalid value '{}' for {}", value, key);
+            match key {
+                "rule" => metadata.rule = value.parse().map_err(|_| invalid())?,
+                // Runs cannot be rebuilt from no cells or with a stride of 0
+                "width" => {
+                    metadata.width = value
+                        .parse()
+                        .ok()
+                        .filter(|width| *width > 0)
+                        .ok_or_else(invalid)?
+                }
+                "height" => metadata.height = value.parse().map_err(|_| invalid())?,
+                "frames" => metadata.frames = value.parse().map_err(|_| invalid())?,
+                "seed" => metadata.seed = Some(value.parse().map_err(|_| invalid())?),
+                "density" => metadata.density = Some(value.parse().map_err(|_| invalid())?),
+                "init" => metadata.init = Some(value.to_string()),
+                "boundary" => metadata.boundary = value.parse()?,
+                "start" => metadata.start = value.parse().map_err(|_| invalid())?,
+                "stride" => {
+                    metadata.stride = value
+                        .parse()
+                        .ok()
+                        .filter(|stride| *stride > 0)
+                        .ok_or_else(invalid)?
+                }
+                "lattice" => metadata.lattice = value.parse()?,
+                "offset" => metadata.offset = value.parse().map_err(|_| invalid())?,
+                "track" => metadata.track = value.parse()?,
+                "strip" => metadata.strip = Some(value.parse().map_err(|_| invalid())?),
+                "bitmap" if value.is_empty() => return Err(invalid()),
+                "bitmap" => metadata.bitmap = value.to_string(),
+                _ => continue,
+            }
+            seen.push(key);
+        }
+        for key in &["rule", "width", "height", "frames", "bitmap"] {
+            if !seen.contains(key) {
+                return Err(format!("Metadata is missing {}", key));
+            }
+        }
+        Ok(metadata)
+    }
+
+    // Reads the metadata from a GIF or PNG built by this crate
+    pub fn read(path: &str) -> Result<Metadata, String> {
+        let mut data = Vec::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_end(&mut data))
+            .map_err(|e| format!("Could not read {}: {}", path, e))?;
+        let comments = if data.starts_with(b"\x89PNG") {
+            png_comments(&data)
+        } else {
+            gif_comments(&data)
+        };
+        let comments = comments.map_err(|e| format!("{}: {}", path, e))?;
+        comments
+            .iter()
+            .map(|c| String::from_utf8_lossy(c))
+            .find(|c| c.starts_with(MAGIC))
+            .map(|c| Metadata::decode(&c))
+            .unwrap_or_else(|| Err(format!("{} has no cellular metadata", path)))
+    }
+
+    // Width of the frames, including the statistics strip
+    pub fn frame_width(&self) -> u16 {
+        StatsStrip::frame_width(self.width, self.strip.is_some())
+    }
+
+    pub fn initial_bitmap(&self) -> BitMap {
+        BitMap::from_bit_string(&self.bitmap)
+    }
+
+    // Simulation of the recorded run from its initial bitmap, before any generations
+    // are skipped
+    pub fn initial_simulation(&self) -> Simulation {
+        Simulation::new(Rule::new(self.rule), self.boundary, self.initial_bitmap())
+    }
+
+    // Simulation of the recorded run, from the generation of its first frame
+    pub fn simulation(&self) -> Simulation {
+        let mut simulation = self.initial_simulation();
+        simulation.advance(self.start);
+        simulation
+    }
+
+    // HashLife run of the recorded rule and initial bitmap, from the generation of its
+    // first frame
+    pub fn hashlife(&self) -> Result<HashLife, String> {
+        let mut life = HashLife::new(Rule::new(self.rule), &self.initial_bitmap())?;
+        life.advance(self.start)?;
+        Ok(life)
+    }
+
+    // Growing lattice of the recorded rule and initial bitmap, from the generation of
+    // its first frame
+    pub fn growing(&self) -> Result<GrowingLattice, String> {
+        let mut lattice = GrowingLattice::new(Rule::new(self.rule), &self.initial_bitmap())?;
+        lattice.advance(self.start)?;
+        Ok(lattice)
+    }
+
+    // Generation of the last row drawn
+    pub fn last_generation(&self) -> u64 {
+        let rows = u64::from(self.height) + u64::from(self.frames.max(1)) - 1;
+        rows.saturating_sub(1)
+            .saturating_mul(self.stride)
+            .saturating_add(self.start)
+    }
+
+    // Cells drawn of each generation
+    pub fn viewport(&self) -> Viewport {
+        Viewport {
+            x0: self.offset,
+            width: self.width,
+            track: self.track,
+        }
+    }
+
+    // Rows of the recorded run, every so many generations from the first frame on
+    pub fn generations(&self) -> Result<Box<dyn Iterator<Item = Generation>>, String> {
+        let (viewport, stride) = (self.viewport(), self.stride);
+        if self.lattice != Lattice::Finite {
+            check_generation(0, self.last_generation())?;
+        }
+        Ok(match self.lattice {
+            Lattice::Finite => Box::new(Cropped::new(
+                self.simulation().step_by(stride as usize),
+                viewport,
+                self.boundary,
+            )),
+            Lattice::Growing => Box::new(self.growing()?.samples(viewport, stride)),
+            Lattice::HashLife => Box::new(self.hashlife()?.samples(viewport, stride)),
+        })
+    }
+
+    // Command line that builds the same animation again
+    pub fn command(&self, output: &str) -> String {
+        // Canvases expanded to the light cone of the initial bitmap are the only frames
+        // wider than it
+        let lattice_width = self.bitmap.len();
+        let expanded = self.lattice != Lattice::Finite
+            && self.track == Track::Fixed
+            && self.offset < 0
+            && i64::from(self.width) == lattice_width as i64 - 2 * self.offset;
+        let width = if expanded {
+            lattice_width
+        } else {
+            self.width.into()
+        };
+        let start = match (&self.init, self.seed) {
+            (Some(init), Some(seed)) => {
+                format!("-w {} --init '{}' -s {}", width, init, seed)
+            }
+            (None, Some(seed)) => format!(
+                "-w {} -d {} -s {}",
+                width,
+                self.density.unwrap_or(0.5),
+                seed
+            ),
+            _ if width != lattice_width => format!("-w {} -b {}", width, self.bitmap),
+            _ => format!("-b {}", self.bitmap),
+        };
+        let boundary = match self.boundary {
+            Boundary::Periodic => String::new(),
+            boundary => format!(" --boundary {}", boundary),
+        };
+        let strip = match self.strip {
+            Some(k) => format!(" --stats-strip --blocks {}", k),
+            None => String::new(),
+        };
+        let mut run = match self.start {
+            0 => String::new(),
+            t => format!(" --start-generation {}", t),
+        };
+        if self.stride > 1 {
+            run += &format!(" --every {}", self.stride);
+        }
+        match self.lattice {
+            Lattice::Finite => {}
+            Lattice::Growing => run += " --growing",
+            Lattice::HashLife => run += " --hashlife",
+        }
+        if expanded {
+            run += " --canvas expand";
+        } else {
+            // Bitmaps given as such set the width themselves
+            if self.seed.is_some() && lattice_width != width {
+                run += &format!(" --lattice-width {}", lattice_width);
+            }
+            if self.offset != Viewport::centred(lattice_width, self.width) {
+                run += &format!(" --window-start {}", self.offset);
+            }
+            if self.track != Track::Fixed {
+                run += &format!(" --track {}", self.track);
+            }
+        }
+        format!(
+            "cellular {} -h {} -f {} -r {}{}{}{} -o {}",
+            start, self.height, self.frames, self.rule, boundary, run, strip, output
+        )
+    }
+}
+
+// Contents of every comment extension in a GIF, found by walking its blocks
+fn gif_comments(data: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    let truncated = || String::from("GIF ends early");
+    if !data.starts_with(b"GIF8") || data.len() < 13 {
+        return Err(String::from("Not a GIF"));
+    }
+    // Colour tables hold 2^(n + 1) RGB triples when the high bit of their flags is set
+    let table_len = |flags: u8| {
+        if flags & 0x80 != 0 {
+            3 << ((flags & 0x07) + 1)
+        } else {
+            0
+        }
+    };
+    let mut pos = 13 + table_len(data[10]);
+    let mut comments = Vec::new();
+    // Data sub-blocks: a length byte followed by that many bytes, ending at length 0
+    let read_sub_blocks = |pos: &mut usize, out: &mut Vec<u8>| -> Result<(), String> {
+        loop {
+            let len = *data.get(*pos).ok_or_else(truncated)? as usize;
+            *pos += 1;
+            if len == 0 {
+                return Ok(());
+            }
+            out.extend_from_slice(data.get(*pos..*pos + len).ok_or_else(truncated)?);
+            *pos += len;
+        }
+    };
+    loop {
+        match data.get(pos) {
+            Some(&EXTENSION) => {
+                let label = *data.get(pos + 1).ok_or_else(truncated)?;
+                pos += 2;
+                let mut contents = Vec::new();
+                read_sub_blocks(&mut pos, &mut contents)?;
+                if label == COMMENT_LABEL {
+                    comments.push(contents);
+                }
+            }
+            Some(&IMAGE) => {
+                let flags = *data.get(pos + 9).ok_or_else(truncated)?;
+                // Descriptor, local colour table and the LZW minimum code size
+                pos += 10 + table_len(flags) + 1;
+                read_sub_blocks(&mut pos, &mut Vec::new())?;
+            }
+            Some(&TRAILER) | None => return Ok(comments),
+            Some(b) => return Err(format!("Unexpected block 0x{:02X}", b)),
+        }
+    }
+}
+
+// Text of every tEXt chunk with the comment keyword in a PNG
+fn png_comments(data: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    let truncated = || String::from("PNG ends early");
+    let mut keyword = PNG_TEXT_KEYWORD.as_bytes().to_vec();
+    keyword.push(0);
+    let mut comments = Vec::new();
+    // Each chunk is a length, a type, the data and a CRC
+    let mut pos = 8;
+    while pos < data.len() {
+        let header = data.get(pos..pos + 8).ok_or_else(truncated)?;
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let contents = data.get(pos + 8..pos + 8 + len).ok_or_else(truncated)?;
+        match &header[4..] {
+            b"tEXt" if contents.starts_with(&keyword) => {
+                comments.push(contents[keyword.len()..].to_vec())
+            }
+            b"IEND" => break,
+            _ => {}
+        }
+        pos += 12 + len;
+    }
+    Ok(comments)
+}
@@ -0,0 +1,152 @@
+use super::bitmap::BitMap;
+
+use std::fmt;
+
+// Error in a textual bitmap, with the 1-based line and column it was found at
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Encoding {
+    Glyphs,
+    Hex,
+    Base64,
+}
+
+// Character of the input along with where it was found
+struct Located {
+    c: char,
+    line: usize,
+    column: usize,
+}
+
+fn error(at: &Located, message: String) -> ParseError {
+    ParseError {
+        line: at.line,
+        column: at.column,
+        message,
+    }
+}
+
+// Parses a bitmap written as text. Whitespace is ignored and # starts a comment that
+// runs to the end of the line. Cells are written as 0/1, ./* or _/X, unless the first
+// row starts with hex: (or 0x) or base64:, in which case the rest of the input holds
+// the bits in that encoding, most significant bit first
+pub fn parse_bitmap(input: &str) -> Result<BitMap, ParseError> {
+    let mut chars: Vec<Located> = Vec::new();
+    for (l, line) in input.lines().enumerate() {
+        let content = match line.find('#') {
+            Some(i) => &line[..i],
+            None => line,
+        };
+        for (c, character) in content.chars().enumerate() {
+            if !character.is_whitespace() {
+                chars.push(Located {
+                    c: character,
+                    line: l + 1,
+                    column: c + 1,
+                });
+            }
+        }
+    }
+
+    let text: String = chars.iter().take(7).map(|l| l.c).collect();
+    let (encoding, skip) = if text.starts_with("hex:") {
+        (Encoding::Hex, 4)
+    } else if text.starts_with("0x") {
+        (Encoding::Hex, 2)
+    } else if text.starts_with("base64:") {
+        (Encoding::Base64, 7)
+    } else {
+        (Encoding::Glyphs, 0)
+    };
+    let chars = &chars[skip..];
+
+    let bits = match encoding {
+        Encoding::Glyphs => decode_glyphs(chars)?,
+        Encoding::Hex => decode_hex(chars)?,
+        Encoding::Base64 => decode_base64(chars)?,
+    };
+
+    if bits.is_empty() {
+        let (line, column) = match chars.last() {
+            Some(l) => (l.line, l.column),
+            None => (input.lines().count().max(1), 1),
+        };
+        return Err(ParseError {
+            line,
+            column,
+            message: String::from("Bitmap contains no cells"),
+        });
+    }
+    Ok(BitMap::from_fn(bits.len() as u64, |i| bits[i]))
+}
+
+fn decode_glyphs(chars: &[Located]) -> Result<Vec<bool>, ParseError> {
+    chars
+        .iter()
+        .map(|l| match l.c {
+            '0' | '.' | '_' => Ok(false),
+            '1' | '*' | 'X' => Ok(true),
+            c => Err(error(
+                l,
+                format!("Unexpected '{}', cells must be one of 0/1, ./* or _/X", c),
+            )),
+        })
+        .collect()
+}
+
+fn decode_hex(chars: &[Located]) -> Result<Vec<bool>, ParseError> {
+    let mut bits = Vec::with_capacity(chars.len() * 4);
+    for l in chars {
+        let digit = match l.c.to_digit(16) {
+            Some(d) => d,
+            None => return Err(error(l, format!("'{}' is not a hexadecimal digit", l.c))),
+        };
+        bits.extend((0..4).rev().map(|b| digit & (1 << b) != 0));
+    }
+    Ok(bits)
+}
+
+fn decode_base64(chars: &[Located]) -> Result<Vec<bool>, ParseError> {
+    let mut bits = Vec::with_capacity(chars.len() * 6);
+    let mut padding = 0;
+    for l in chars {
+        let value = match l.c {
+            'A'..='Z' => l.c as u32 - 'A' as u32,
+            'a'..='z' => l.c as u32 - 'a' as u32 + 26,
+            '0'..='9' => l.c as u32 - '0' as u32 + 52,
+            '+' | '-' => 62,
+            '/' | '_' => 63,
+            '=' => {
+                padding += 1;
+                continue;
+            }
+            c => return Err(error(l, format!("'{}' is not a base64 character", c))),
+        };
+        if padding > 0 {
+            return Err(error(
+                l,
+                String::from("Base64 data continues after padding"),
+            ));
+        }
+        bits.extend((0..6).rev().map(|b| value & (1 << b) != 0));
+    }
+    // Drop the bits that only exist to fill out the last character
+    bits.truncate(bits.len() / 8 * 8);
+    Ok(bits)
+}
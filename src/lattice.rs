@@ -0,0 +1,240 @@
+use crate::image_manip::bitmap::BitMap;
+use crate::rule::Rule;
+use crate::simulation::Generation;
+use crate::viewport::{Track, Viewport};
+
+use std::fmt;
+use std::str::FromStr;
+
+// What the cells of a run live on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lattice {
+    // The cells of the initial bitmap, with a Boundary beyond either edge
+    Finite,
+    // Unbounded lattice of empty cells, stepped one generation at a time
+    Growing,
+    // Unbounded lattice of empty cells, run with HashLife
+    HashLife,
+}
+
+impl FromStr for Lattice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Lattice, String> {
+        match s {
+            "finite" => Ok(Lattice::Finite),
+            "growing" => Ok(Lattice::Growing),
+            "hashlife" => Ok(Lattice::HashLife),
+            _ => Err(format!(
+                "Unknown lattice '{}', expected finite, growing or hashlife",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Lattice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Lattice::Finite => write!(f, "finite"),
+            Lattice::Growing => write!(f, "growing"),
+            Lattice::HashLife => write!(f, "hashlife"),
+        }
+    }
+}
+
+// Lattice with a cell at every position, all of them empty but for finitely many. Cell i
+// of the initial bitmap is at position i
+pub trait Unbounded {
+    fn generation(&self) -> u64;
+
+    // Fails without advancing past MAX_GENERATION
+    fn advance(&mut self, steps: u64) -> Result<(), String>;
+
+    // Cells from position x0 on, as many as width
+    fn row(&self, x0: i64, width: usize) -> BitMap;
+
+    // Positions of the leftmost and rightmost occupied cells, if there are any
+    fn bounds(&self) -> Option<(i64, i64)>;
+
+    // Mean position of the occupied cells, if there are any
+    fn centre_of_mass(&self) -> Option<f64>;
+
+    // The cells of the viewport every so many generations from the current one, for
+    // drawing like the generations of a Simulation
+    fn samples(self, viewport: Viewport, every: u64) -> Samples<Self>
+    where
+        Self: Sized,
+    {
+        Samples {
+            lattice: self,
+            viewport,
+            every: every.max(1),
+            first: None,
+            left: viewport.x0,
+        }
+    }
+}
+
+// Last generation unbounded lattices run to. Patterns then span less than 2^58 cells,
+// so positions and the blocks of HashLife stay well within an i64
+pub const MAX_GENERATION: u64 = 1 << 56;
+
+// Whether a lattice at generation can advance by steps
+pub fn check_generation(generation: u64, steps: u64) -> Result<(), String> {
+    match generation.checked_add(steps) {
+        Some(last) if last <= MAX_GENERATION => Ok(()),
+        _ => Err(format!(
+            "Unbounded lattices can run up to generation {} at most",
+            MAX_GENERATION
+        )),
+    }
+}
+
+// Rules that turn three empty cells occupied fill an unbounded lattice at once
+pub fn check_quiescent(rule: Rule) -> Result<(), String> {
+    if rule.number() & 1 != 0 {
+        return Err(format!(
+            "Rule {} fills empty space, so it cannot run on an unbounded lattice",
+            rule.number()
+        ));
+    }
+    Ok(())
+}
+
+// Generations of an unbounded lattice as seen through a viewport, every so many
+// generations. Like a Simulation it yields the current generation first
+#[derive(Debug, Clone)]
+pub struct Samples<L: Unbounded> {
+    lattice: L,
+    viewport: Viewport,
+    every: u64,
+    // Index of the first generation yielded, and the leftmost cell drawn last
+    first: Option<u64>,
+    left: i64,
+}
+
+impl<L: Unbounded> Iterator for Samples<L> {
+    type Item = Generation;
+
+    fn next(&mut self) -> Option<Generation> {
+        if self.first.is_some() {
+            self.lattice.advance(self.every).ok()?;
+        }
+        let index = self.lattice.generation();
+        let first = *self.first.get_or_insert(index);
+        let centre = match self.viewport.track {
+            Track::Mass => self.lattice.centre_of_mass(),
+            _ => None,
+        };
+        self.left = self.viewport.left(index - first, centre, self.left);
+        Some(Generation {
+            index,
+            cells: self.lattice.row(self.left, self.viewport.width.into()),
+        })
+    }
+}
+
+// Unbounded lattice stored as the cells between its leftmost and rightmost occupied
+// ones. Each generation they can reach one cell further on either side, the edge of the
+// light cone of the initial bitmap, so nothing ever wraps around
+#[derive(Debug, Clone)]
+pub struct GrowingLattice {
+    rule: Rule,
+    cells: Vec<bool>,
+    // Position of the first of cells
+    origin: i64,
+    generation: u64,
+}
+
+impl GrowingLattice {
+    pub fn new(rule: Rule, cells: &BitMap) -> Result<GrowingLattice, String> {
+        check_quiescent(rule)?;
+        let mut lattice = GrowingLattice {
+            rule,
+            cells: cells.get_vec(),
+            origin: 0,
+            generation: 0,
+        };
+        lattice.trim();
+        Ok(lattice)
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    // Number of cells stored, from the leftmost occupied one to the rightmost
+    pub fn width(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn step(&mut self) {
+        let len = self.cells.len() as i64;
+        let cell = |i: i64| (0..len).contains(&i) && self.cells[i as usize];
+        // Pattern bit 0b100 is the cell above, as in BitMap::rule_step
+        let next: Vec<bool> = (-1..len + 1)
+            .map(|i| {
+                let pattern = (cell(i + 1) as u8) << 2 | (cell(i) as u8) << 1 | cell(i - 1) as u8;
+                self.rule.number() >> pattern & 1 == 1
+            })
+            .collect();
+        self.cells = next;
+        self.origin -= 1;
+        self.generation += 1;
+        self.trim();
+    }
+
+    // Drops empty cells from either end
+    fn trim(&mut self) {
+        let first = match self.cells.iter().position(|c| *c) {
+            Some(first) => first,
+            None => {
+                self.cells.clear();
+                return;
+            }
+        };
+        let last = self.cells.iter().rposition(|c| *c).unwrap();
+        self.cells.truncate(last + 1);
+        self.cells.drain(..first);
+        self.origin += first as i64;
+    }
+}
+
+impl Unbounded for GrowingLattice {
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn advance(&mut self, steps: u64) -> Result<(), String> {
+        check_generation(self.generation, steps)?;
+        for _ in 0..steps {
+            self.step();
+        }
+        Ok(())
+    }
+
+    fn row(&self, x0: i64, width: usize) -> BitMap {
+        BitMap::from_fn(width as u64, |i| {
+            let i = x0 + i as i64 - self.origin;
+            i >= 0 && (i as usize) < self.cells.len() && self.cells[i as usize]
+        })
+    }
+
+    fn bounds(&self) -> Option<(i64, i64)> {
+        match self.cells.len() {
+            0 => None,
+            len => Some((self.origin, self.origin + len as i64 - 1)),
+        }
+    }
+
+    fn centre_of_mass(&self) -> Option<f64> {
+        let (count, sum) = (0..self.cells.len())
+            .filter(|i| self.cells[*i])
+            .fold((0, 0.0), |(c, s), i| (c + 1, s + i as f64));
+        if count == 0 {
+            return None;
+        }
+        Some(self.origin as f64 + sum / count as f64)
+    }
+}
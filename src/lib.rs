@@ -1,3 +1,18 @@
+pub mod analysis;
+pub mod atlas;
+pub mod cancel;
+pub mod checkpoint;
+pub mod classify;
 pub mod cli;
+pub mod damage;
+pub mod hashlife;
 pub mod image_manip;
+pub mod lattice;
+pub mod output;
 pub mod prog;
+pub mod report;
+pub mod rule;
+pub mod simulation;
+pub mod sink;
+pub mod sweep;
+pub mod viewport;
@@ -1,72 +1,1009 @@
+use cellular::analysis::{StatsFormat, StatsSink, StatsStrip};
+use cellular::atlas::build_atlas;
+use cellular::cancel;
+use cellular::checkpoint::{self, render_checkpointed, Checkpoint};
+use cellular::classify::{Classification, Classifier};
 use cellular::cli;
-use cellular::image_manip::bitmap::BitMap;
-use cellular::image_manip::build_gif;
-use cellular::prog::{Message, ProgBar};
+use cellular::cli::Command;
+use cellular::damage::{
+    lyapunov_ensemble, write_derrida_chart, Damage, DamageGeneration, DamageImage, Derrida,
+    DerridaPlot,
+};
+use cellular::image_manip::bitmap::{BitMap, Boundary};
+use cellular::image_manip::init::Init;
+use cellular::image_manip::metadata::Metadata;
+use cellular::image_manip::{
+    generations_drawn, is_still, render_file, render_gif_to_writer, render_to_writer,
+};
+use cellular::lattice::{check_generation, Lattice};
+use cellular::output::{check_overwrite, AtomicFile, CountingWriter};
+use cellular::prog::{Message, ProgBar, Verbosity};
+use cellular::report::{sidecar_path, Stats};
+use cellular::rule::Rule;
+use cellular::simulation::{Generation, Simulation};
+use cellular::sink::{skip, GifSink, Sink, Window};
+use cellular::sweep::{format_output, report_run, run_sweep, Sweep};
+use cellular::viewport::{Cropped, Track, Viewport};
+
+use gif::EncodingError;
+use indicatif::HumanBytes;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
 use std::convert::Into;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
 use std::process::exit;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
 use std::thread;
+use std::time::{Duration, Instant};
+
+// Conventional exit code of a process stopped by SIGINT
+const INTERRUPTED_CODE: i32 = 130;
+// Cells of the initial bitmap gif info prints before eliding the rest
+const BITMAP_SHOWN: usize = 1024;
 
 fn main() {
-    let args = cli::parse_args().unwrap();
+    let mut args = match cli::parse_args().unwrap() {
+        Command::Render(args) => args,
+        Command::Classify {
+            rules,
+            classifier,
+            json,
+        } => {
+            cancel::install_handler();
+            print_classes(&rules, &classifier, json);
+            return;
+        }
+        Command::Equivalents(rule) => {
+            println!("Rule {}", rule);
+            print_equivalents(rule);
+            return;
+        }
+        Command::Info(rule) => {
+            println!("Rule {}", rule);
+            print_notations(rule);
+            print_equivalents(rule);
+            print_properties(rule);
+            return;
+        }
+        Command::GifInfo { path, rerun, force } => {
+            gif_info(&path, rerun.as_deref(), force);
+            return;
+        }
+        Command::Resume {
+            checkpoint,
+            segment,
+            force,
+            disable_prog,
+            verbosity,
+        } => {
+            cancel::install_handler();
+            resume(
+                &checkpoint,
+                segment.as_deref(),
+                force,
+                disable_prog,
+                verbosity,
+            );
+            return;
+        }
+    };
+    cancel::install_handler();
     let mut init_line: BitMap;
 
+    if let Some(sweep) = &args.sweep {
+        build_sweep(&args, sweep);
+        return;
+    }
+
+    if let Some(seeds) = &args.lyapunov {
+        print_lyapunov(&args, seeds);
+        return;
+    }
+
+    if let Some(derrida) = &args.derrida {
+        build_derrida(&args, derrida);
+        return;
+    }
+
     if args.random {
-        init_line = BitMap::random(args.width.into(), args.density);
+        let init = args.init.clone().unwrap_or(Init::Random(args.density));
+        let cells = args.lattice_width.unwrap_or_else(|| args.width.into());
+        init_line = match init.build(cells, args.seed) {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(cli::FAILURE_CODE);
+            }
+        };
     } else {
-        init_line = args.bitmap.unwrap();
+        init_line = args.bitmap.take().unwrap();
+        if args.continued {
+            // The last row is already in the earlier animation, and the next one is a
+            // stride of generations after it
+            for _ in 0..args.every {
+                init_line.rule_step_with_boundary(args.rule, args.boundary);
+            }
+        }
     }
     let steps = args.steps;
     let output: String = args.output.clone();
 
-    if args.disable_prog {
-        match build_gif(
-            args.width,
-            args.height,
-            args.steps,
-            &mut init_line,
-            args.output.as_str(),
-            None,
-            args.rule,
-        ) {
-            Ok(_) => {}
-            Err(_) => {
-                println!("Error building {}", args.output);
-                exit(1);
-            }
+    let mut metadata = Metadata::new(args.rule, args.width, args.height, steps, &init_line);
+    metadata.boundary = args.boundary;
+    metadata.start = args.start_generation;
+    metadata.stride = args.every;
+    metadata.lattice = args.lattice;
+    metadata.offset = args
+        .window_start
+        .unwrap_or_else(|| Viewport::centred(init_line.size(), args.width));
+    metadata.track = args.track;
+    let outside =
+        metadata.offset < 0 || metadata.offset + i64::from(args.width) > init_line.size() as i64;
+    if args.lattice == Lattice::Finite && args.boundary != Boundary::Periodic && outside {
+        // Cells beyond the edges are only simulated on periodic or unbounded lattices
+        eprintln!(
+            "The window of cells {} to {} lies outside the {} cells of the lattice",
+            metadata.offset,
+            metadata.offset + i64::from(args.width) - 1,
+            init_line.size()
+        );
+        exit(cli::FAILURE_CODE);
+    }
+    if args.stats_strip {
+        metadata.strip = Some(args.blocks);
+    }
+    if is_still(&output) {
+        metadata.frames = 1;
+    }
+    if args.random {
+        metadata.seed = args.seed;
+        match &args.init {
+            Some(init) => metadata.init = Some(init.to_string()),
+            None => metadata.density = Some(args.density),
+        }
+    }
+    let last = metadata.last_generation();
+    if args.lattice != Lattice::Finite {
+        if let Err(e) = check_generation(0, last) {
+            eprintln!("{}", e);
+            exit(cli::FAILURE_CODE);
+        }
+    }
+    if args.expand_canvas {
+        // The light cone widens by a cell a side every generation up to the last drawn
+        let width = last
+            .saturating_mul(2)
+            .saturating_add(init_line.size() as u64);
+        let widest = if args.stats_strip {
+            StatsStrip::MAX_CELLS
+        } else {
+            u16::MAX
+        };
+        if width > widest.into() {
+            eprintln!(
+                "The {} generations drawn reach {} cells, more than the {} an image can be wide",
+                last, width, widest
+            );
+            exit(cli::FAILURE_CODE);
+        }
+        metadata.width = width as u16;
+        metadata.offset = -(last as i64);
+    }
+
+    if let Some(cell) = args.damage {
+        build_damage(&args, metadata.simulation(), cell);
+        return;
+    }
+
+    // The report is only written once the run is done, so it is checked for up front
+    if args.reporting.sidecar {
+        let sidecar = sidecar_path(&output);
+        if let Err(e) = check_overwrite(std::iter::once(sidecar.as_str()), args.force) {
+            eprintln!("{}", e);
+            exit(cli::FAILURE_CODE);
         }
+    }
+
+    // Written next to the output and moved into place once complete
+    let mut atomic = if output == cli::STDOUT {
+        None
     } else {
-        let (progress_tx, progress_rx) = mpsc::channel();
-        let mut progress_bar = ProgBar::new(&output, steps);
-
-        let progress_thread = thread::spawn(move || loop {
-            match progress_rx.try_recv() {
-                Ok(msg) => match msg {
-                    Message::Update(val) => progress_bar.update((val + 1).into()),
-                    Message::Kill => return,
-                },
-                Err(_) => {}
+        match AtomicFile::new(&output, args.force) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(cli::FAILURE_CODE);
+            }
+        }
+    };
+    let target = match &atomic {
+        Some(file) => file.temp_name(),
+        None => output.clone(),
+    };
+
+    if args.checkpoint.is_some() {
+        if let Some(file) = &mut atomic {
+            file.keep_partial();
+        }
+    }
+
+    // Streamed while the output is drawn, so it is not written atomically
+    let mut stats_sink = args.stats.as_ref().map(|path| {
+        let writer: Box<dyn Write> = if path == cli::STDOUT {
+            Box::new(BufWriter::new(io::stdout()))
+        } else {
+            match File::create(path) {
+                Ok(file) => Box::new(BufWriter::new(file)),
+                Err(e) => {
+                    eprintln!("Could not create {}: {}", path, e);
+                    exit(cli::FAILURE_CODE);
+                }
+            }
+        };
+        StatsSink::new(writer, StatsFormat::from_path(path), args.blocks)
+    });
+
+    // Bytes of output written so far, for the progress bar and summary
+    let bytes = Arc::new(AtomicU64::new(0));
+    let started = Instant::now();
+    let build =
+        |progress_bar_tx_wrap: Option<&Sender<Message>>| -> Result<Option<Stats>, EncodingError> {
+            let generations: Box<dyn Iterator<Item = Generation>> =
+                if metadata.lattice != Lattice::Finite {
+                    // The command line only allows rules unbounded lattices can run
+                    metadata.generations().unwrap()
+                } else {
+                    let mut simulation = metadata.initial_simulation();
+                    skip(&mut simulation, metadata.start, progress_bar_tx_wrap);
+                    Box::new(Cropped::new(
+                        simulation.step_by(metadata.stride as usize),
+                        metadata.viewport(),
+                        metadata.boundary,
+                    ))
+                };
+            let generations: Box<dyn Iterator<Item = Generation>> = match stats_sink.as_mut() {
+                Some(stats_sink) => Box::new(stats_sink.recording(generations)),
+                None => generations,
             };
+            match &args.atlas {
+                Some(atlas) => build_atlas(
+                    atlas,
+                    args.width,
+                    args.height,
+                    args.steps,
+                    &init_line,
+                    &target,
+                    progress_bar_tx_wrap,
+                )
+                .map(|_| None),
+                None if args.output == cli::STDOUT => render_to_writer(
+                    generations,
+                    &metadata,
+                    CountingWriter::new(BufWriter::new(io::stdout()), bytes.clone()),
+                    false,
+                    progress_bar_tx_wrap,
+                )
+                .map(Some),
+                None => {
+                    let writer = CountingWriter::new(File::create(&target)?, bytes.clone());
+                    match args.checkpoint {
+                        Some(every) => render_checkpointed(
+                            generations.take(generations_drawn(&metadata, &output) as usize),
+                            GifSink::animation(writer, &metadata)?,
+                            Checkpoint::new(&metadata, &output, &target, every),
+                            bytes.clone(),
+                            progress_bar_tx_wrap,
+                        ),
+                        None => render_to_writer(
+                            generations,
+                            &metadata,
+                            writer,
+                            is_still(&output),
+                            progress_bar_tx_wrap,
+                        ),
+                    }
+                    .map(Some)
+                }
+            }
+        };
+
+    let progress_bar = if args.disable_prog {
+        None
+    } else {
+        let total = match &args.atlas {
+            // A still atlas is a single frame
+            Some(_) if is_still(&output) => 1,
+            Some(_) => steps,
+            None => generations_drawn(&metadata, &output) as u32,
+        };
+        match &args.atlas {
+            // The atlas is written by name, so its size is only known at the end
+            Some(_) => Some(ProgBar::new(&output, total).with_unit("frame")),
+            None => Some(
+                ProgBar::new(&output, total)
+                    .with_bytes(bytes.clone())
+                    .with_skipped(metadata.start),
+            ),
+        }
+    };
+    let stats = match with_progress(progress_bar, build) {
+        Ok(stats) => stats,
+        Err(_) => {
+            eprintln!("Error building {}", args.output);
+            drop(atomic);
+            exit(cli::FAILURE_CODE);
+        }
+    };
+
+    if let Some(Err(e)) = stats_sink.as_mut().map(|sink| sink.finish()) {
+        eprintln!(
+            "Could not write statistics to {}: {}",
+            args.stats.as_deref().unwrap_or_default(),
+            e
+        );
+        exit(cli::FAILURE_CODE);
+    }
+
+    if let Some(file) = atomic {
+        if let Err(e) = file.commit() {
+            eprintln!("Could not write {}: {}", output, e);
+            exit(cli::FAILURE_CODE);
+        }
+    }
+
+    if args.verbosity == Verbosity::Verbose && !cancel::is_cancelled() {
+        let written = match &args.atlas {
+            Some(_) => fs::metadata(&output).map(|m| m.len()).unwrap_or(0),
+            None => bytes.load(Ordering::Relaxed),
+        };
+        let drawn = match &args.atlas {
+            Some(_) if is_still(&output) => (1, "frames"),
+            Some(_) => (steps.into(), "frames"),
+            None => (generations_drawn(&metadata, &output), "generations"),
+        };
+        print_summary(&output, drawn, started.elapsed(), written, stats.as_ref());
+    }
+
+    if let Some(stats) = stats.filter(|_| args.reporting.enabled()) {
+        if let Err(e) = report_run(&args.reporting, &output, &metadata, &stats) {
+            eprintln!("Could not write report for {}: {}", output, e);
+            exit(cli::FAILURE_CODE);
+        }
+    }
+
+    if cancel::is_cancelled() {
+        if args.verbosity != Verbosity::Quiet {
+            eprintln!("Interrupted, {} holds the frames built so far", output);
+            if args.checkpoint.is_some() {
+                eprintln!(
+                    "Carry on with: cellular --resume {}",
+                    Checkpoint::path(&output)
+                );
+            }
+        }
+        exit(INTERRUPTED_CODE);
+    }
+    if args.checkpoint.is_some() {
+        if let Err(e) = checkpoint::remove(&output) {
+            eprintln!("Could not remove the checkpoint of {}: {}", output, e);
+        }
+    }
+}
+
+// Draws the damage run from flipping cell and prints its measurements as CSV
+fn build_damage(args: &cli::CLIArgs, simulation: Simulation, cell: usize) {
+    let output = &args.output;
+    let damage = match Damage::new(simulation, cell) {
+        Ok(damage) => damage,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(cli::FAILURE_CODE);
+        }
+    };
+    let atomic = match AtomicFile::new(output, args.force) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(cli::FAILURE_CODE);
+        }
+    };
+    let file = match File::create(atomic.temp_path()) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Could not create {}: {}", output, e);
+            exit(cli::FAILURE_CODE);
+        }
+    };
+
+    let mut image = DamageImage::new(BufWriter::new(file), args.width, is_still(output));
+    let build = |progress_bar_tx_wrap: Option<&Sender<Message>>| {
+        let mut stdout = io::stdout();
+        writeln!(stdout, "{}", DamageGeneration::csv_header())?;
+        let mut last = None;
+        for (done, generation) in damage.take(args.height.into()).enumerate() {
+            if cancel::is_cancelled() {
+                break;
+            }
+            image.push(&generation);
+            writeln!(stdout, "{}", generation.to_csv())?;
+            if let Some(progress_bar_tx) = progress_bar_tx_wrap {
+                progress_bar_tx.send(Message::Update(done as u32)).unwrap();
+            }
+            last = Some(generation);
+        }
+        image.finish()?;
+        Ok(last)
+    };
+    let progress_bar = if args.disable_prog {
+        None
+    } else {
+        Some(ProgBar::new(output, args.height.into()))
+    };
+    let last = match with_progress(progress_bar, build) {
+        Ok(last) => last,
+        Err(_) => {
+            drop(atomic);
+            // Nothing is written if the run was interrupted before its first generation
+            if cancel::is_cancelled() {
+                exit(INTERRUPTED_CODE);
+            }
+            eprintln!("Error building {}", output);
+            exit(cli::FAILURE_CODE);
+        }
+    };
+    if let Err(e) = atomic.commit() {
+        eprintln!("Could not write {}: {}", output, e);
+        exit(cli::FAILURE_CODE);
+    }
+
+    if args.verbosity != Verbosity::Quiet {
+        if let Some(last) = last {
+            match last.front_speeds() {
+                Some((left, right)) => eprintln!(
+                    "Damage from cell {}: {} cells differ at generation {}, fronts moving {:.3} cells per generation to the left and {:.3} to the right",
+                    cell, last.hamming, last.index, left, right
+                ),
+                None => eprintln!(
+                    "Damage from cell {}: the copies agree at generation {}",
+                    cell, last.index
+                ),
+            }
+        }
+    }
+    if cancel::is_cancelled() {
+        exit(INTERRUPTED_CODE);
+    }
+}
+
+// Estimates the maximal Lyapunov exponent over a random run for each seed
+fn print_lyapunov(args: &cli::CLIArgs, seeds: &[u64]) {
+    let init = args.init.clone().unwrap_or(Init::Random(args.density));
+    let rule = Rule::new(args.rule);
+    let simulations = seeds
+        .iter()
+        .map(|seed| match init.build(args.width.into(), Some(*seed)) {
+            Ok(cells) => Simulation::new(rule, args.boundary, cells),
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(cli::FAILURE_CODE);
+            }
         });
+    let cell = args.damage.unwrap_or(args.width as usize / 2);
+    let estimate = lyapunov_ensemble(simulations, cell, args.height.into());
+    match (estimate.mean, estimate.std_dev) {
+        (Some(mean), Some(std_dev)) => println!(
+            "Rule {}: maximal Lyapunov exponent {:.4} ± {:.4} over {} runs",
+            rule,
+            mean,
+            std_dev,
+            estimate.runs - estimate.died
+        ),
+        _ => println!("Rule {}: damage died out in every run", rule),
+    }
+    if estimate.died > 0 && estimate.died < estimate.runs {
+        println!(
+            "  damage died out in {} of {} runs",
+            estimate.died, estimate.runs
+        );
+    }
+}
+
+// Prints the Derrida plot of each rule as CSV, and draws them all to a chart if one is given
+fn build_derrida(args: &cli::CLIArgs, derrida: &Derrida) {
+    let atomic = derrida
+        .chart
+        .as_ref()
+        .map(|chart| match AtomicFile::new(chart, args.force) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(cli::FAILURE_CODE);
+            }
+        });
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let build = |progress_bar_tx_wrap: Option<&Sender<Message>>| {
+        let mut stdout = io::stdout();
+        writeln!(stdout, "{}", DerridaPlot::csv_header())?;
+        let mut plots = Vec::with_capacity(derrida.rules.len());
+        for (done, rule) in derrida.rules.iter().enumerate() {
+            if cancel::is_cancelled() {
+                break;
+            }
+            let plot = DerridaPlot::measure(
+                *rule,
+                args.width.into(),
+                args.boundary,
+                args.density,
+                derrida,
+                &mut rng,
+            );
+            for line in plot.to_csv() {
+                writeln!(stdout, "{}", line)?;
+            }
+            if let Some(progress_bar_tx) = progress_bar_tx_wrap {
+                progress_bar_tx.send(Message::Update(done as u32)).unwrap();
+            }
+            plots.push(plot);
+        }
+        Ok(plots)
+    };
+    let progress_bar = if args.disable_prog {
+        None
+    } else {
+        let label = format!("Derrida plots of {} rules", derrida.rules.len());
+        Some(ProgBar::new(&label, derrida.rules.len() as u32).with_unit("rule"))
+    };
+    let plots = match with_progress(progress_bar, build) {
+        Ok(plots) => plots,
+        Err(_) => {
+            eprintln!("Error writing Derrida plots");
+            exit(cli::FAILURE_CODE);
+        }
+    };
+
+    if let (Some(atomic), Some(chart)) = (atomic, &derrida.chart) {
+        let written = File::create(atomic.temp_path())
+            .and_then(|file| write_derrida_chart(BufWriter::new(file), &plots));
+        if let Err(e) = written.and_then(|_| atomic.commit()) {
+            eprintln!("Could not write {}: {}", chart, e);
+            exit(cli::FAILURE_CODE);
+        }
+    }
+
+    if args.verbosity != Verbosity::Quiet {
+        for plot in &plots {
+            if let Some(slope) = plot.slope() {
+                let behaviour = if slope > 1.0 { "chaotic" } else { "ordered" };
+                eprintln!(
+                    "Rule {}: slope {:.3} at the origin ({})",
+                    plot.rule, slope, behaviour
+                );
+            }
+        }
+    }
+    if cancel::is_cancelled() {
+        exit(INTERRUPTED_CODE);
+    }
+}
+
+// Runs build, with a progress bar drawn by a thread of its own unless there is none
+fn with_progress<T, F>(progress_bar: Option<ProgBar>, build: F) -> Result<T, EncodingError>
+where
+    F: FnOnce(Option<&Sender<Message>>) -> Result<T, EncodingError>,
+{
+    let progress_bar = match progress_bar {
+        Some(progress_bar) => progress_bar,
+        None => return build(None),
+    };
+    let (progress_tx, progress_rx) = mpsc::channel();
+    let progress_thread = thread::spawn(move || progress_bar.run(progress_rx));
+    let result = build(Some(&progress_tx));
+    // The bar stops once every sender is gone, if the build has not stopped it already
+    drop(progress_tx);
+    progress_thread.join().unwrap();
+    result
+}
+
+// Carries on the run of a checkpoint, appending to the frames it wrote if they can be
+// found and segment is not given, or writing the frames left to a new animation
+fn resume(
+    path: &str,
+    segment: Option<&str>,
+    force: bool,
+    disable_prog: bool,
+    verbosity: Verbosity,
+) {
+    let checkpoint = match Checkpoint::read(path) {
+        Ok(checkpoint) => checkpoint,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(cli::FAILURE_CODE);
+        }
+    };
+    let partial = match segment {
+        Some(_) => None,
+        None => checkpoint.find_partial().map(String::from),
+    };
+    let failed = |e: &dyn std::fmt::Display| -> ! {
+        eprintln!("Could not resume from {}: {}", path, e);
+        exit(cli::FAILURE_CODE);
+    };
+
+    let (output, metadata, mut next, offset) = match &partial {
+        Some(_) => (
+            checkpoint.output.clone(),
+            checkpoint.metadata.clone(),
+            checkpoint.clone(),
+            checkpoint.bytes,
+        ),
+        None => {
+            let output = match segment {
+                Some(output) => String::from(output),
+                None => segment_name(&checkpoint.output, checkpoint.frames_done),
+            };
+            let metadata = checkpoint.segment_metadata();
+            let next = Checkpoint::new(&metadata, &output, "", checkpoint.every);
+            (output, metadata, next, 0)
+        }
+    };
+    // The destination of an appended run is the interrupted output itself
+    let mut atomic = match AtomicFile::new(&output, force || partial.is_some()) {
+        Ok(file) => file,
+        Err(e) => failed(&e),
+    };
+    atomic.keep_partial();
+    next.partial = atomic.temp_name();
+    next.output = output.clone();
+
+    let file = match &partial {
+        Some(partial) => fs::rename(partial, atomic.temp_path())
+            .and_then(|_| OpenOptions::new().write(true).open(atomic.temp_path()))
+            .and_then(|mut file| {
+                // Drops frames after the checkpoint and the trailer
+                file.set_len(offset)?;
+                file.seek(SeekFrom::End(0))?;
+                Ok(file)
+            }),
+        None => File::create(atomic.temp_path()),
+    };
+    let file = file.unwrap_or_else(|e| failed(&e));
+    // A crash from here on resumes from the new partial file
+    if let Err(e) = next.write(&Checkpoint::path(&output)) {
+        failed(&e);
+    }
+
+    let left = next.generations_left();
+    let generations = next.simulation().take(left as usize);
+    let bytes = Arc::new(AtomicU64::new(offset));
+    let writer = CountingWriter::new(file, bytes.clone());
+    let started = Instant::now();
+    let build = |progress_bar_tx_wrap: Option<&Sender<Message>>| match &partial {
+        Some(_) => render_checkpointed(
+            generations,
+            Window::for_metadata(GifSink::appending(writer, &metadata)?, &metadata),
+            next.clone(),
+            bytes.clone(),
+            progress_bar_tx_wrap,
+        ),
+        None => render_checkpointed(
+            generations,
+            GifSink::animation(writer, &metadata)?,
+            next.clone(),
+            bytes.clone(),
+            progress_bar_tx_wrap,
+        ),
+    };
+    let progress_bar = if disable_prog {
+        None
+    } else {
+        Some(ProgBar::new(&output, left as u32).with_bytes(bytes.clone()))
+    };
+    let stats = match with_progress(progress_bar, build) {
+        Ok(stats) => stats,
+        Err(_) => {
+            eprintln!("Error building {}", output);
+            exit(cli::FAILURE_CODE);
+        }
+    };
+    if let Err(e) = atomic.commit() {
+        eprintln!("Could not write {}: {}", output, e);
+        exit(cli::FAILURE_CODE);
+    }
+
+    if cancel::is_cancelled() {
+        if verbosity != Verbosity::Quiet {
+            eprintln!("Interrupted, {} holds the frames built so far", output);
+            eprintln!(
+                "Carry on with: cellular --resume {}",
+                Checkpoint::path(&output)
+            );
+        }
+        exit(INTERRUPTED_CODE);
+    }
+    if verbosity == Verbosity::Verbose {
+        let written = bytes.load(Ordering::Relaxed) - offset;
+        print_summary(
+            &output,
+            (left, "generations"),
+            started.elapsed(),
+            written,
+            Some(&stats),
+        );
+    }
+    if let Err(e) = checkpoint::remove(&output) {
+        eprintln!("Could not remove the checkpoint of {}: {}", output, e);
+    }
+}
 
-        match build_gif(
+// Default output for the frames left of a run, e.g. out_from500.gif
+fn segment_name(output: &str, frames_done: u32) -> String {
+    let path = Path::new(output);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}_from{}.gif", stem, frames_done))
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn build_sweep(args: &cli::CLIArgs, sweep: &Sweep) {
+    let outputs: Vec<String> = sweep
+        .runs()
+        .iter()
+        .map(|run| format_output(&sweep.template, run, args.width, args.height, args.steps))
+        .collect();
+    let sidecars: Vec<String> = outputs
+        .iter()
+        .filter(|_| sweep.reporting.sidecar)
+        .map(|output| sidecar_path(output))
+        .collect();
+    let written = outputs.iter().chain(&sidecars).map(String::as_str);
+    if let Err(e) = check_overwrite(written, args.force) {
+        eprintln!("{}", e);
+        exit(cli::FAILURE_CODE);
+    }
+
+    let init_line = args.bitmap.as_ref();
+    let started = Instant::now();
+    let build = |progress_bar_tx_wrap: Option<&Sender<Message>>| {
+        Ok(run_sweep(
+            sweep,
             args.width,
             args.height,
             args.steps,
-            &mut init_line,
-            args.output.as_str(),
-            Some(&progress_tx),
-            args.rule,
-        ) {
-            Ok(_) => {}
-            Err(_) => {
-                println!("Error building {}", args.output);
-                (&progress_tx).send(Message::Kill).unwrap();
+            init_line,
+            args.init.as_ref(),
+            progress_bar_tx_wrap,
+        ))
+    };
+    let progress_bar = if args.disable_prog {
+        None
+    } else {
+        let num_runs = sweep.runs().len();
+        Some(ProgBar::new(&format!("{} runs", num_runs), num_runs as u32).with_unit("run"))
+    };
+    // Runs that fail are returned rather than stopping the sweep, so this cannot fail
+    let failures = with_progress(progress_bar, build).unwrap_or_default();
+
+    if !failures.is_empty() {
+        for (output, _) in &failures {
+            eprintln!("Error building {}", output);
+        }
+        exit(cli::FAILURE_CODE);
+    }
+    if cancel::is_cancelled() {
+        if args.verbosity != Verbosity::Quiet {
+            eprintln!("Interrupted, runs that had not finished were discarded");
+        }
+        exit(INTERRUPTED_CODE);
+    }
+    if args.verbosity == Verbosity::Verbose {
+        let written: u64 = outputs
+            .iter()
+            .filter_map(|output| fs::metadata(output).ok())
+            .map(|m| m.len())
+            .sum();
+        let runs = (outputs.len() as u64, "runs");
+        print_summary(&sweep.template, runs, started.elapsed(), written, None);
+    }
+}
+
+fn gif_info(path: &str, rerun: Option<&str>, force: bool) {
+    let metadata = match Metadata::read(path) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(cli::FAILURE_CODE);
+        }
+    };
+
+    if let Some(output) = rerun {
+        let generations = match metadata.generations() {
+            Ok(generations) => generations,
+            Err(e) => {
+                eprintln!("Could not rebuild {}: {}", path, e);
                 exit(cli::FAILURE_CODE);
             }
+        };
+        let built = if output == cli::STDOUT {
+            let stdout = BufWriter::new(io::stdout());
+            render_gif_to_writer(generations, &metadata, stdout, None).map(|_| ())
+        } else {
+            AtomicFile::new(output, force)
+                .map_err(|e| e.into())
+                .and_then(|file| {
+                    render_file(generations, &metadata, &file.temp_name(), None)?;
+                    Ok(file.commit()?)
+                })
+        };
+        if let Err(e) = built {
+            eprintln!("Error building {}: {}", output, e);
+            exit(cli::FAILURE_CODE);
         }
+        return;
+    }
 
-        progress_thread.join().unwrap();
+    println!("{} (cellular {})", path, metadata.version);
+    println!("  rule:     {}", metadata.rule);
+    println!("  width:    {}", metadata.width);
+    println!("  height:   {}", metadata.height);
+    println!("  frames:   {}", metadata.frames);
+    if let Some(seed) = metadata.seed {
+        println!("  seed:     {}", seed);
+    }
+    if let Some(density) = metadata.density {
+        println!("  density:  {}", density);
+    }
+    if let Some(init) = &metadata.init {
+        println!("  init:     {}", init);
+    }
+    match metadata.lattice {
+        Lattice::Finite => println!("  boundary: {}", metadata.boundary),
+        Lattice::Growing => println!("  lattice:  unbounded, growing"),
+        Lattice::HashLife => println!("  lattice:  unbounded, run with hashlife"),
+    }
+    if metadata.bitmap.len() != usize::from(metadata.width) || metadata.offset != 0 {
+        println!(
+            "  window:   cells {} to {} of the initial {}",
+            metadata.offset,
+            metadata.offset + i64::from(metadata.width) - 1,
+            metadata.bitmap.len()
+        );
+    }
+    if metadata.track != Track::Fixed {
+        println!("  track:    {}", metadata.track);
+    }
+    if metadata.start > 0 {
+        println!("  start:    generation {}", metadata.start);
     }
+    if metadata.stride > 1 {
+        println!("  every:    {} generations", metadata.stride);
+    }
+    if let Some(k) = metadata.strip {
+        println!("  strip:    blocks of {}", k);
+    }
+    // Lattices far wider than the image would fill the screen
+    match metadata.bitmap.get(..BITMAP_SHOWN) {
+        Some(shown) if metadata.bitmap.len() > BITMAP_SHOWN => {
+            println!("  bitmap:   {}... ({} cells)", shown, metadata.bitmap.len())
+        }
+        _ => println!("  bitmap:   {}", metadata.bitmap),
+    }
+    println!("  command:  {}", metadata.command(path));
+}
+
+// Goes to stderr, as stdout may hold the output or reports
+fn print_summary(
+    output: &str,
+    (count, unit): (u64, &str),
+    elapsed: Duration,
+    bytes: u64,
+    stats: Option<&Stats>,
+) {
+    let secs = elapsed.as_secs_f64();
+    let mut line = format!(
+        "Built {}: {} {} in {:.2}s ({:.0} {}/s), {}",
+        output,
+        count,
+        unit,
+        secs,
+        count as f64 / secs.max(f64::EPSILON),
+        unit,
+        HumanBytes(bytes)
+    );
+    if let Some(stats) = stats {
+        line.push_str(&format!(
+            " (simulation {:.2}s, encoding {:.2}s)",
+            stats.simulation_time.as_secs_f64(),
+            stats.encoding_time.as_secs_f64()
+        ));
+    }
+    eprintln!("{}", line);
+}
+
+// Guesses the Wolfram class of each rule, stopping after the rule in progress on Ctrl-C
+fn print_classes(rules: &[u8], classifier: &Classifier, json: bool) {
+    for rule in rules {
+        if cancel::is_cancelled() {
+            exit(INTERRUPTED_CODE);
+        }
+        let classification = classifier.classify(*rule);
+        if json {
+            println!("{}", classification.to_json());
+        } else {
+            print_classification(&classification);
+        }
+    }
+}
+
+fn print_classification(classification: &Classification) {
+    let e = &classification.evidence;
+    println!(
+        "Rule {}: class {} ({}), confidence {:.2}",
+        classification.rule,
+        classification.class,
+        classification.class.name(),
+        classification.confidence
+    );
+    println!("  uniform:    {:.2} of runs", e.uniform);
+    match (e.transient, e.period) {
+        (Some(transient), Some(period)) => println!(
+            "  cycles:     {:.2} of runs, periods up to {} after up to {} generations",
+            e.cycled, period, transient
+        ),
+        _ => println!("  cycles:     none"),
+    }
+    println!(
+        "  density:    {:.3}, fluctuating by {:.3}",
+        e.density, e.density_fluctuation
+    );
+    println!("  damage:     {:.3} of cells", e.damage);
+    println!("  complexity: {:.3}", e.complexity);
+    println!("  regularity: {:.3}", e.regularity);
+}
+
+fn print_notations(rule: Rule) {
+    println!("  binary:            {}", rule.to_binary());
+    println!("  hexadecimal:       {}", rule.to_hex());
+    println!("  table:             {}", rule.to_table());
+    println!("  formula:           {}", rule.to_formula());
+}
+
+fn print_properties(rule: Rule) {
+    let properties = rule.properties();
+    let yes_no = |b: bool| if b { "yes" } else { "no" };
+    println!("  lambda:            {}", properties.lambda);
+    println!("  additive:          {}", yes_no(properties.additive));
+    println!(
+        "  number-conserving: {}",
+        yes_no(properties.number_conserving)
+    );
+    println!("  left-permutive:    {}", yes_no(properties.left_permutive));
+    println!(
+        "  right-permutive:   {}",
+        yes_no(properties.right_permutive)
+    );
+    println!("  surjective:        {}", yes_no(properties.surjective));
+    println!("  injective:         {}", yes_no(properties.injective));
+}
+
+fn print_equivalents(rule: Rule) {
+    let class: Vec<String> = rule
+        .equivalence_class()
+        .iter()
+        .map(|r| r.to_string())
+        .collect();
+    println!("  mirror:            {}", rule.mirror());
+    println!("  complement:        {}", rule.complement());
+    println!("  mirror-complement: {}", rule.conjugate());
+    println!("  class:             {}", class.join(", "));
+    println!("  canonical:         {}", rule.canonical());
 }
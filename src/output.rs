@@ -0,0 +1,154 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+// Output written to a temporary file in the same directory as its destination and
+// renamed into place by commit, so that the destination never holds a partial file.
+// The temporary file is removed if it is dropped without being committed
+pub struct AtomicFile {
+    temp: PathBuf,
+    destination: PathBuf,
+    committed: bool,
+    // Whether the temporary file survives being dropped
+    keep: bool,
+}
+
+impl AtomicFile {
+    // Fails with AlreadyExists if the destination exists, unless force is set
+    pub fn new(destination: &str, force: bool) -> io::Result<AtomicFile> {
+        let destination = PathBuf::from(destination);
+        if !force && destination.exists() {
+            return Err(already_exists(&destination));
+        }
+        let name = match destination.file_name() {
+            Some(name) => name.to_string_lossy(),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} is not a file name", destination.display()),
+                ))
+            }
+        };
+        // Keeps the extension, which decides the output format
+        let temp = destination.with_file_name(format!(".cellular-{}-{}", process::id(), name));
+        Ok(AtomicFile {
+            temp,
+            destination,
+            committed: false,
+            keep: false,
+        })
+    }
+
+    // Where to write the output until it is committed
+    pub fn temp_path(&self) -> &Path {
+        &self.temp
+    }
+
+    pub fn temp_name(&self) -> String {
+        self.temp.to_string_lossy().into_owned()
+    }
+
+    // Leaves the temporary file in place if this is dropped without being committed,
+    // so that a checkpointed run can be resumed from it
+    pub fn keep_partial(&mut self) {
+        self.keep = true;
+    }
+
+    // Makes sure the contents reach the disk, then moves them to the destination
+    pub fn commit(mut self) -> io::Result<()> {
+        File::open(&self.temp)?.sync_all()?;
+        fs::rename(&self.temp, &self.destination)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed && !self.keep {
+            // The file may never have been created
+            let _ = fs::remove_file(&self.temp);
+        }
+    }
+}
+
+// Checks that none of the outputs exist, unless they may be overwritten
+pub fn check_overwrite<'a, I: IntoIterator<Item = &'a str>>(
+    outputs: I,
+    force: bool,
+) -> io::Result<()> {
+    if force {
+        return Ok(());
+    }
+    match outputs.into_iter().map(Path::new).find(|p| p.exists()) {
+        Some(path) => Err(already_exists(path)),
+        None => Ok(()),
+    }
+}
+
+// Passes writes on to inner, adding the number of bytes written to a counter that
+// another thread, e.g. the progress bar, can read
+pub struct CountingWriter<W: Write> {
+    inner: W,
+    count: Arc<AtomicU64>,
+}
+
+impl<W: Write> CountingWriter<W> {
+    pub fn new(inner: W, count: Arc<AtomicU64>) -> CountingWriter<W> {
+        CountingWriter { inner, count }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count.fetch_add(written as u64, Ordering::Relaxed);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Passes writes on to inner except for the first skip bytes, e.g. to leave out the
+// header of a GIF whose frames are appended to an existing one
+pub struct SkipWriter<W: Write> {
+    inner: W,
+    skip: usize,
+}
+
+impl<W: Write> SkipWriter<W> {
+    pub fn new(inner: W, skip: usize) -> SkipWriter<W> {
+        SkipWriter { inner, skip }
+    }
+}
+
+impl<W: Write> Write for SkipWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.skip >= buf.len() {
+            self.skip -= buf.len();
+            return Ok(buf.len());
+        }
+        let skipped = self.skip;
+        self.skip = 0;
+        Ok(skipped + self.inner.write(&buf[skipped..])?)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn already_exists(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!(
+            "{} already exists, use --force to overwrite it",
+            path.display()
+        ),
+    )
+}
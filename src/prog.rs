@@ -1,22 +1,56 @@
-use indicatif::{ProgressBar, ProgressStyle};
-use std::convert::TryInto;
+use indicatif::{HumanBytes, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::Duration;
 
 pub enum Message {
+    // Index of the last generation, frame or run done
     Update(u32),
+    // Generations simulated so far without being drawn, before the first frame
+    Skipped(u64),
+    // Time spent so far on each phase of a run, which interleave for animations
+    Timing {
+        simulation: Duration,
+        encoding: Duration,
+    },
+    // Every generation is done and the output is being written, e.g. a PNG
+    Encoding,
     Kill,
 }
 
+// How much is printed while building
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Verbosity {
+    // Errors only
+    Quiet,
+    // Progress bar
+    #[default]
+    Normal,
+    // Progress bar and a summary of each build
+    Verbose,
+}
+
 pub struct ProgBar {
     bar: ProgressBar,
     outfile: String,
+    // What Update counts, for the rate
+    unit: String,
+    // Bytes written so far, shared with the writer of the output
+    bytes: Option<Arc<AtomicU64>>,
+    timing: Option<(Duration, Duration)>,
+    // Generations skipped before the first frame, which Update counts on from
+    skipped: u64,
 }
 
 impl ProgBar {
     pub fn new(outfile: &String, full_val: u32) -> ProgBar {
-        let bar = ProgressBar::new(full_val.try_into().unwrap());
+        // Always stderr, leaving stdout free for output streamed to it
+        let bar = ProgressBar::with_draw_target(full_val.into(), ProgressDrawTarget::stderr());
         bar.set_style(
             ProgressStyle::default_bar()
-                .template("{prefix} [{wide_bar:.green/red}] {percent}%")
+                .template("{prefix} [{wide_bar:.green/red}] {percent}% ETA {eta} {msg}")
                 .progress_chars("==-"),
         );
         bar.set_prefix(format!("Building: '{}'", outfile));
@@ -24,22 +58,105 @@ impl ProgBar {
         ProgBar {
             bar,
             outfile: outfile.clone(),
+            unit: String::from("gen"),
+            bytes: None,
+            timing: None,
+            skipped: 0,
         }
     }
 
-    pub fn update(&mut self, val: u64) {
-        if val == self.bar.length() {
-            self.bar.set_position(self.bar.length());
-            self.bar.set_style(
-                ProgressStyle::default_bar()
-                    .template("{prefix:.green} [{wide_bar:.green/red}] {percent}%")
-                    .progress_chars("==-"),
-            );
-            self.bar.set_prefix(format!("Done: '{}'", self.outfile));
-            self.bar.finish();
-        }
+    pub fn with_unit(mut self, unit: &str) -> ProgBar {
+        self.unit = String::from(unit);
+        self
+    }
+
+    // Shows the value of bytes, which the output's writer adds to
+    pub fn with_bytes(mut self, bytes: Arc<AtomicU64>) -> ProgBar {
+        self.bytes = Some(bytes);
+        self
+    }
+
+    // Counts generations skipped before the first frame as well as those drawn
+    pub fn with_skipped(mut self, generations: u64) -> ProgBar {
         self.bar
-            .set_message(format!("Building: '{}'", self.outfile));
+            .set_length(self.bar.length().saturating_add(generations));
+        self.skipped = generations;
+        self
+    }
+
+    pub fn update(&mut self, val: u64) {
         self.bar.set_position(val);
+        self.bar.set_message(self.status());
+    }
+
+    // Bar position, counting skipped generations
+    pub fn position(&self) -> u64 {
+        self.bar.position()
+    }
+
+    pub fn length(&self) -> u64 {
+        self.bar.length()
+    }
+
+    // Draws the messages until Kill or until every sender is gone, then hands the
+    // finished bar back. Blocks between messages, so it is meant for a thread of its own
+    pub fn run(mut self, progress_rx: Receiver<Message>) -> ProgBar {
+        while let Ok(msg) = progress_rx.recv() {
+            match msg {
+                Message::Update(val) => self.update(self.skipped + u64::from(val) + 1),
+                Message::Skipped(val) => self.update(val),
+                Message::Timing {
+                    simulation,
+                    encoding,
+                } => self.timing = Some((simulation, encoding)),
+                Message::Encoding => {
+                    self.bar.set_prefix(format!("Encoding: '{}'", self.outfile));
+                    self.bar.set_message(self.status());
+                }
+                Message::Kill => break,
+            }
+        }
+        self.finish();
+        self
+    }
+
+    fn finish(&mut self) {
+        if self.bar.position() < self.bar.length() {
+            // Failed or interrupted
+            self.bar.abandon();
+            return;
+        }
+        self.bar.set_style(
+            ProgressStyle::default_bar()
+                .template("{prefix:.green} [{wide_bar:.green/red}] {percent}% in {elapsed} {msg}")
+                .progress_chars("==-"),
+        );
+        self.bar.set_prefix(format!("Done: '{}'", self.outfile));
+        self.bar.finish_with_message(self.status());
+    }
+
+    // Rate, bytes written and the split of time between simulation and encoding
+    fn status(&self) -> String {
+        let mut parts = Vec::new();
+        let secs = self.bar.elapsed().as_secs_f64();
+        if secs > 0.0 {
+            let rate = self.bar.position() as f64 / secs;
+            parts.push(format!("{:.0} {}/s", rate, self.unit));
+        }
+        if let Some(bytes) = &self.bytes {
+            parts.push(format!("{}", HumanBytes(bytes.load(Ordering::Relaxed))));
+        }
+        if let Some((simulation, encoding)) = self.timing {
+            let total = (simulation + encoding).as_secs_f64();
+            if total > 0.0 {
+                let share = simulation.as_secs_f64() / total * 100.0;
+                parts.push(format!(
+                    "simulation {:.0}% encoding {:.0}%",
+                    share,
+                    100.0 - share
+                ));
+            }
+        }
+        parts.join(", ")
     }
 }
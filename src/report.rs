@@ -0,0 +1,192 @@
+use crate::image_manip::metadata::Metadata;
+use crate::output::AtomicFile;
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+// Where run reports go: a .json file next to each output and/or stdout
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Reporting {
+    pub sidecar: bool,
+    pub stdout: bool,
+}
+
+impl Reporting {
+    pub fn enabled(&self) -> bool {
+        self.sidecar || self.stdout
+    }
+}
+
+// First generation of a cycle and the number of generations it repeats after
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cycle {
+    pub start: u64,
+    pub period: u64,
+}
+
+// Finds the first generation that repeats an earlier one. Generations are deterministic
+// and the lattice is finite, so every later generation then repeats with that period
+#[derive(Default)]
+pub struct CycleDetector {
+    // Hash of each generation seen so far and the generation it was first seen at
+    seen: HashMap<u64, u64>,
+    cycle: Option<Cycle>,
+}
+
+impl CycleDetector {
+    pub fn new() -> CycleDetector {
+        CycleDetector::default()
+    }
+
+    // cells holds one byte per cell, as in the rows of a frame
+    pub fn observe(&mut self, generation: u64, cells: &[u8]) {
+        if self.cycle.is_some() {
+            return;
+        }
+        let mut hasher = DefaultHasher::new();
+        cells.hash(&mut hasher);
+        if let Some(start) = self.seen.insert(hasher.finish(), generation) {
+            self.cycle = Some(Cycle {
+                start,
+                period: generation - start,
+            });
+            self.seen.clear();
+        }
+    }
+
+    pub fn cycle(&self) -> Option<Cycle> {
+        self.cycle
+    }
+}
+
+// Measurements taken while building an animation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub simulation_time: Duration,
+    pub encoding_time: Duration,
+    // Fraction of occupied cells in the last generation drawn
+    pub final_density: f64,
+    pub cycle: Option<Cycle>,
+}
+
+pub struct Report<'a> {
+    pub output: &'a str,
+    pub metadata: &'a Metadata,
+    pub stats: &'a Stats,
+    pub bytes: u64,
+}
+
+impl<'a> Report<'a> {
+    // Reads the size of the finished output from disk
+    pub fn new(
+        output: &'a str,
+        metadata: &'a Metadata,
+        stats: &'a Stats,
+    ) -> io::Result<Report<'a>> {
+        Ok(Report {
+            output,
+            metadata,
+            stats,
+            bytes: fs::metadata(output)?.len(),
+        })
+    }
+
+    // Single line of JSON, so that sweeps print one report per line
+    pub fn to_json(&self) -> String {
+        let m = self.metadata;
+        let parameters = [
+            ("rule", m.rule.to_string()),
+            ("width", m.width.to_string()),
+            ("height", m.height.to_string()),
+            ("frames", m.frames.to_string()),
+            ("seed", json_option(m.seed)),
+            ("density", json_option(m.density)),
+            ("init", json_option(m.init.as_deref().map(json_string))),
+            ("boundary", json_string(&m.boundary.to_string())),
+            ("start", m.start.to_string()),
+            ("stride", m.stride.to_string()),
+            ("lattice", json_string(&m.lattice.to_string())),
+            ("offset", m.offset.to_string()),
+            ("track", json_string(&m.track.to_string())),
+            ("bitmap", json_string(&m.bitmap)),
+            ("version", json_string(&m.version)),
+        ];
+        let s = self.stats;
+        let timing = [
+            (
+                "simulation_seconds",
+                s.simulation_time.as_secs_f64().to_string(),
+            ),
+            (
+                "encoding_seconds",
+                s.encoding_time.as_secs_f64().to_string(),
+            ),
+        ];
+        let cycle = [
+            ("detected", s.cycle.is_some().to_string()),
+            ("start", json_option(s.cycle.map(|c| c.start))),
+            ("period", json_option(s.cycle.map(|c| c.period))),
+        ];
+        json_object(&[
+            ("output", json_string(self.output)),
+            ("parameters", json_object(&parameters)),
+            ("timing", json_object(&timing)),
+            ("bytes", self.bytes.to_string()),
+            ("final_density", s.final_density.to_string()),
+            ("cycle", json_object(&cycle)),
+        ])
+    }
+
+    // Writes the report next to the output, replacing its extension with .json. An
+    // existing report is replaced, as callers check for one along with the output
+    pub fn write_sidecar(&self) -> io::Result<()> {
+        let file = AtomicFile::new(&sidecar_path(self.output), true)?;
+        fs::write(file.temp_path(), self.to_json() + "\n")?;
+        file.commit()
+    }
+}
+
+pub fn sidecar_path(output: &str) -> String {
+    Path::new(output)
+        .with_extension("json")
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn json_object(fields: &[(&str, String)]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("{}:{}", json_string(key), value))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+pub fn json_option<T: ToString>(value: Option<T>) -> String {
+    match value {
+        Some(v) => v.to_string(),
+        None => String::from("null"),
+    }
+}
+
+pub fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
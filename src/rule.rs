@@ -0,0 +1,217 @@
+mod formula;
+mod properties;
+
+pub use properties::RuleProperties;
+
+use std::fmt;
+use std::str::FromStr;
+
+// Elementary cellular automaton rule in Wolfram's numbering: bit n of the rule is the
+// next state of a cell whose neighbourhood (left, centre, right) spells n in binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rule(u8);
+
+impl Rule {
+    pub fn new(number: u8) -> Rule {
+        Rule(number)
+    }
+
+    pub fn number(&self) -> u8 {
+        self.0
+    }
+
+    // Next state of the centre cell of the given neighbourhood
+    pub fn apply(&self, left: bool, centre: bool, right: bool) -> bool {
+        let neighbourhood = (left as u8) << 2 | (centre as u8) << 1 | right as u8;
+        self.0 & (1 << neighbourhood) != 0
+    }
+
+    // Rule that behaves the same under left-right reflection of the lattice
+    pub fn mirror(&self) -> Rule {
+        let mut number = 0;
+        for n in 0..8 {
+            let reflected = (n & 0b100) >> 2 | (n & 0b010) | (n & 0b001) << 2;
+            if self.0 & (1 << reflected) != 0 {
+                number |= 1 << n;
+            }
+        }
+        Rule(number)
+    }
+
+    // Rule that behaves the same with the roles of 0 and 1 swapped
+    pub fn complement(&self) -> Rule {
+        let mut number = 0;
+        for n in 0..8 {
+            if self.0 & (1 << (7 - n)) == 0 {
+                number |= 1 << n;
+            }
+        }
+        Rule(number)
+    }
+
+    // Mirror of the complement, which is also the complement of the mirror
+    pub fn conjugate(&self) -> Rule {
+        self.mirror().complement()
+    }
+
+    // Distinct rules reachable by mirroring and complementing, sorted in ascending order
+    pub fn equivalence_class(&self) -> Vec<Rule> {
+        let mut class = vec![*self, self.mirror(), self.complement(), self.conjugate()];
+        class.sort();
+        class.dedup();
+        class
+    }
+
+    // Lowest numbered rule of the equivalence class
+    pub fn canonical(&self) -> Rule {
+        self.equivalence_class()[0]
+    }
+
+    pub fn is_canonical(&self) -> bool {
+        self.canonical() == *self
+    }
+
+    // Canonical representative of each of the 88 inequivalent elementary rules
+    pub fn representatives() -> Vec<Rule> {
+        (0..=u8::MAX)
+            .map(Rule)
+            .filter(|rule| rule.is_canonical())
+            .collect()
+    }
+
+    pub fn to_binary(&self) -> String {
+        format!("0b{:08b}", self.0)
+    }
+
+    pub fn to_hex(&self) -> String {
+        format!("0x{:02X}", self.0)
+    }
+
+    // Lookup table from each neighbourhood to its next state, e.g. "111:0 110:1 ... 000:0"
+    pub fn to_table(&self) -> String {
+        let entries: Vec<String> = (0..8)
+            .rev()
+            .map(|n| format!("{:03b}:{}", n, (self.0 >> n) & 1))
+            .collect();
+        entries.join(" ")
+    }
+
+    // Number of cells either side of a cell that its next state depends on
+    pub fn radius(&self) -> usize {
+        1
+    }
+
+    // Next state of each neighbourhood, indexed by the neighbourhood read as a binary number
+    pub fn outputs(&self) -> Vec<bool> {
+        (0..8).map(|n| (self.0 >> n) & 1 == 1).collect()
+    }
+
+    // Langton's lambda and the additivity, number conservation, permutivity, surjectivity
+    // and injectivity of the rule
+    pub fn properties(&self) -> RuleProperties {
+        RuleProperties::of_table(self.radius(), &self.outputs())
+    }
+
+    // Algebraic normal form over l, c and r, e.g. "l xor r" for rule 90
+    pub fn to_formula(&self) -> String {
+        formula::algebraic_normal_form(self.0)
+    }
+
+    fn parse_table(table: &str) -> Result<Rule, String> {
+        let mut number: u8 = 0;
+        let mut seen: u8 = 0;
+        for entry in table.split_whitespace() {
+            let (neighbourhood, state) = match entry.split_once(':') {
+                Some(parts) => parts,
+                None => return Err(format!("Table entry '{}' must look like 110:1", entry)),
+            };
+            let n = match u8::from_str_radix(neighbourhood, 2) {
+                Ok(n) if neighbourhood.len() == 3 => n,
+                _ => {
+                    return Err(format!(
+                        "'{}' is not a neighbourhood of 3 cells",
+                        neighbourhood
+                    ))
+                }
+            };
+            if seen & (1 << n) != 0 {
+                return Err(format!(
+                    "Neighbourhood {} appears more than once",
+                    neighbourhood
+                ));
+            }
+            seen |= 1 << n;
+            match state {
+                "1" => number |= 1 << n,
+                "0" => {}
+                _ => return Err(format!("Next state of {} must be 0 or 1", neighbourhood)),
+            }
+        }
+        if seen != u8::MAX {
+            let missing: Vec<String> = (0..8)
+                .rev()
+                .filter(|n| seen & (1 << n) == 0)
+                .map(|n| format!("{:03b}", n))
+                .collect();
+            return Err(format!("Table is missing {}", missing.join(", ")));
+        }
+        Ok(Rule(number))
+    }
+
+    fn parse_radix(digits: &str, radix: u32, max_len: usize) -> Result<Rule, String> {
+        match u8::from_str_radix(digits, radix) {
+            Ok(n) if digits.len() <= max_len => Ok(Rule(n)),
+            _ => Err(format!(
+                "'{}' is not an 8 bit integer in base {}",
+                digits, radix
+            )),
+        }
+    }
+}
+
+// Accepts a Wolfram code in decimal (110), binary (0b01101110) or hexadecimal (0x6E),
+// a lookup table ("111:0 110:1 101:1 100:0 011:1 010:1 001:1 000:0") or a boolean
+// formula over l, c and r ("(c xor r) or (not l and c)")
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Rule, String> {
+        let s = s.trim();
+        if let Some(digits) = s.strip_prefix("0b").or_else(|| s.strip_prefix("0B")) {
+            Rule::parse_radix(digits, 2, 8)
+        } else if let Some(digits) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            Rule::parse_radix(digits, 16, 2)
+        } else if !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()) {
+            match s.parse::<u64>() {
+                Ok(n) if n <= u8::MAX as u64 => Ok(Rule(n as u8)),
+                _ => Err(format!(
+                    "Rule number {} is not between 0 and {}",
+                    s,
+                    u8::MAX
+                )),
+            }
+        } else if s.contains(':') {
+            Rule::parse_table(s)
+        } else {
+            formula::parse(s).map(Rule)
+        }
+    }
+}
+
+impl From<u8> for Rule {
+    fn from(number: u8) -> Rule {
+        Rule(number)
+    }
+}
+
+impl From<Rule> for u8 {
+    fn from(rule: Rule) -> u8 {
+        rule.0
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
@@ -0,0 +1,243 @@
+// Boolean formulas over the neighbourhood variables l, c and r
+//
+// Grammar, from lowest to highest precedence:
+//   or   := xor ("or" xor)*
+//   xor  := and ("xor" and)*
+//   and  := not ("and" not)*
+//   not  := "not" not | atom
+//   atom := "l" | "c" | "r" | "0" | "1" | "(" or ")"
+// Operators may also be written as |, ^, & and ! (or ~).
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Var(u8),
+    Const(bool),
+    And,
+    Or,
+    Xor,
+    Not,
+    Open,
+    Close,
+}
+
+enum Expr {
+    Var(u8),
+    Const(bool),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Xor(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    // neighbourhood holds l, c and r in bits 2, 1 and 0
+    fn eval(&self, neighbourhood: u8) -> bool {
+        match self {
+            Expr::Var(bit) => neighbourhood & bit != 0,
+            Expr::Const(value) => *value,
+            Expr::Not(e) => !e.eval(neighbourhood),
+            Expr::And(a, b) => a.eval(neighbourhood) && b.eval(neighbourhood),
+            Expr::Or(a, b) => a.eval(neighbourhood) || b.eval(neighbourhood),
+            Expr::Xor(a, b) => a.eval(neighbourhood) ^ b.eval(neighbourhood),
+        }
+    }
+}
+
+// Splits the formula into tokens, each paired with its character offset
+fn tokenize(formula: &str) -> Result<Vec<(usize, Token)>, String> {
+    let chars: Vec<char> = formula.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c.is_ascii_alphanumeric() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            let token = match word.to_lowercase().as_str() {
+                "l" => Token::Var(0b100),
+                "c" => Token::Var(0b010),
+                "r" => Token::Var(0b001),
+                "0" => Token::Const(false),
+                "1" => Token::Const(true),
+                "and" => Token::And,
+                "or" => Token::Or,
+                "xor" => Token::Xor,
+                "not" => Token::Not,
+                _ => return Err(format!("Unknown word '{}' at position {}", word, start + 1)),
+            };
+            tokens.push((start, token));
+            continue;
+        }
+        let (token, len) = match (c, chars.get(i + 1)) {
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('&', _) => (Token::And, 1),
+            ('|', _) => (Token::Or, 1),
+            ('^', _) => (Token::Xor, 1),
+            ('!', _) | ('~', _) => (Token::Not, 1),
+            ('(', _) => (Token::Open, 1),
+            (')', _) => (Token::Close, 1),
+            _ => {
+                return Err(format!(
+                    "Unexpected character '{}' at position {}",
+                    c,
+                    i + 1
+                ))
+            }
+        };
+        tokens.push((i, token));
+        i += len;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    len: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).map(|(_, t)| *t)
+    }
+
+    // Character offset of the next token, for error messages
+    fn position(&self) -> usize {
+        match self.tokens.get(self.pos) {
+            Some((offset, _)) => offset + 1,
+            None => self.len + 1,
+        }
+    }
+
+    fn binary(
+        &mut self,
+        op: Token,
+        next: fn(&mut Parser) -> Result<Expr, String>,
+        build: fn(Box<Expr>, Box<Expr>) -> Expr,
+    ) -> Result<Expr, String> {
+        let mut expr = next(self)?;
+        while self.peek() == Some(op) {
+            self.pos += 1;
+            expr = build(Box::new(expr), Box::new(next(self)?));
+        }
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        self.binary(Token::Or, Parser::xor, Expr::Or)
+    }
+
+    fn xor(&mut self) -> Result<Expr, String> {
+        self.binary(Token::Xor, Parser::and, Expr::Xor)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        self.binary(Token::And, Parser::not, Expr::And)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some(Token::Not) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let position = self.position();
+        let token = match self.peek() {
+            Some(token) => token,
+            None => return Err(format!("Formula ends early at position {}", position)),
+        };
+        self.pos += 1;
+        match token {
+            Token::Var(bit) => Ok(Expr::Var(bit)),
+            Token::Const(value) => Ok(Expr::Const(value)),
+            Token::Open => {
+                let expr = self.or()?;
+                if self.peek() != Some(Token::Close) {
+                    return Err(format!("Expected ')' at position {}", self.position()));
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            _ => Err(format!(
+                "Expected l, c, r, 0, 1 or '(' at position {}",
+                position
+            )),
+        }
+    }
+}
+
+// Compiles a formula such as "l xor (c or r)" to the rule number it computes
+pub fn parse(formula: &str) -> Result<u8, String> {
+    let mut parser = Parser {
+        tokens: tokenize(formula)?,
+        pos: 0,
+        len: formula.chars().count(),
+    };
+    let expr = parser.or()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(format!(
+            "Unexpected input at position {}",
+            parser.position()
+        ));
+    }
+    let mut number = 0;
+    for neighbourhood in 0..8 {
+        if expr.eval(neighbourhood) {
+            number |= 1 << neighbourhood;
+        }
+    }
+    Ok(number)
+}
+
+// Algebraic normal form of the rule: an exclusive or of conjunctions, which is unique
+// for every rule, e.g. "l xor r" for rule 90
+pub fn algebraic_normal_form(number: u8) -> String {
+    // Möbius transform: coefficient of monomial m is the parity of f over all subsets of m
+    let mut coefficients = [false; 8];
+    for (monomial, coefficient) in coefficients.iter_mut().enumerate() {
+        for subset in 0..8 {
+            if subset & !monomial == 0 && number & (1 << subset) != 0 {
+                *coefficient = !*coefficient;
+            }
+        }
+    }
+
+    // Order terms by degree, then with l before c before r
+    let mut monomials: Vec<usize> = (0..8).filter(|m| coefficients[*m]).collect();
+    monomials.sort_by_key(|m| (m.count_ones(), std::cmp::Reverse(*m)));
+    // Constant rules, where "1" on its own would read as the decimal rule 1
+    match monomials.as_slice() {
+        [] => return String::from("0"),
+        [0] => return String::from("not 0"),
+        _ => {}
+    }
+    let multiple_terms = monomials.len() > 1;
+    let terms: Vec<String> = monomials
+        .iter()
+        .map(|m| {
+            let vars: Vec<&str> = [(0b100, "l"), (0b010, "c"), (0b001, "r")]
+                .iter()
+                .filter(|(bit, _)| m & bit != 0)
+                .map(|(_, name)| *name)
+                .collect();
+            match vars.len() {
+                0 => String::from("1"),
+                1 => String::from(vars[0]),
+                _ if multiple_terms => format!("({})", vars.join(" and ")),
+                _ => vars.join(" and "),
+            }
+        })
+        .collect();
+    terms.join(" xor ")
+}
@@ -0,0 +1,140 @@
+// Properties of the lookup table of a rule of any radius. Tables list the next state of
+// each neighbourhood of 2 * radius + 1 cells, indexed by the neighbourhood read as a binary
+// number with the leftmost cell most significant, as in Wolfram codes
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RuleProperties {
+    // Langton's lambda: the fraction of neighbourhoods whose next state is occupied, with
+    // empty cells as the quiescent state
+    pub lambda: f64,
+    // Linear over GF(2): the image of the sum of two configurations is the sum of their
+    // images
+    pub additive: bool,
+    // Keeps the number of occupied cells of every periodic configuration
+    pub number_conserving: bool,
+    // Flipping the leftmost or rightmost cell of any neighbourhood flips its next state
+    pub left_permutive: bool,
+    pub right_permutive: bool,
+    // Every periodic configuration has a preimage, or has exactly one. In one dimension
+    // these match surjectivity and injectivity on all configurations
+    pub surjective: bool,
+    pub injective: bool,
+}
+
+impl RuleProperties {
+    pub fn of_table(radius: usize, table: &[bool]) -> RuleProperties {
+        let cells = 2 * radius + 1;
+        assert!(
+            table.len() == 1 << cells,
+            "table must have 2^(2r+1) entries"
+        );
+        let (surjective, injective) = surjective_injective(cells, table);
+        RuleProperties {
+            lambda: table.iter().filter(|s| **s).count() as f64 / table.len() as f64,
+            additive: additive(cells, table),
+            number_conserving: number_conserving(cells, table),
+            left_permutive: permutive(table, 1 << (cells - 1)),
+            right_permutive: permutive(table, 1),
+            surjective,
+            injective,
+        }
+    }
+}
+
+// A linear map is the sum of its images of single occupied cells
+fn additive(cells: usize, table: &[bool]) -> bool {
+    (0..table.len()).all(|n| {
+        let sum = (0..cells)
+            .filter(|bit| n & (1 << bit) != 0)
+            .fold(false, |sum, bit| sum ^ table[1 << bit]);
+        table[n] == sum
+    })
+}
+
+fn permutive(table: &[bool], cell: usize) -> bool {
+    (0..table.len()).all(|n| table[n] != table[n ^ cell])
+}
+
+// Boccara and Fukś (1998): f(x_1, ..., x_n) must equal
+// x_1 + sum over k of f(0^k, x_2, ..., x_{n-k+1}) - f(0^k, x_1, ..., x_{n-k})
+fn number_conserving(cells: usize, table: &[bool]) -> bool {
+    let f = |n: usize| table[n] as i64;
+    (0..table.len()).all(|n| {
+        let leftmost = (n >> (cells - 1)) as i64;
+        let without_leftmost = n & ((1 << (cells - 1)) - 1);
+        // Shifting right by k puts k empty cells in front of the first n - k cells
+        let sum: i64 = (1..cells)
+            .map(|k| f(without_leftmost >> (k - 1)) - f(n >> k))
+            .sum();
+        f(n) == leftmost + sum
+    })
+}
+
+// Sutner (1991): pairs of paths through the de Bruijn graph of the rule that spell the
+// same image. The rule is surjective unless two paths can split and meet again, and
+// injective if it is surjective and no pair of differing paths can go on forever
+fn surjective_injective(cells: usize, table: &[bool]) -> (bool, bool) {
+    // Nodes of the de Bruijn graph are the 2r cells two neighbourhoods overlap in
+    let nodes = 1 << (cells - 1);
+    let mask = nodes - 1;
+    let pair = |a: usize, b: usize| a * nodes + b;
+    let successors = |node: usize| {
+        let (a, b) = (node / nodes, node % nodes);
+        let mut next = Vec::with_capacity(4);
+        for x in 0..2 {
+            for y in 0..2 {
+                let (na, nb) = ((a << 1) | x, (b << 1) | y);
+                if table[na] == table[nb] {
+                    next.push(pair(na & mask, nb & mask));
+                }
+            }
+        }
+        next
+    };
+    let diagonal = |node: usize| node / nodes == node % nodes;
+
+    // Pairs of differing paths that split from the diagonal
+    let mut seen = vec![false; nodes * nodes];
+    let mut stack: Vec<usize> = (0..nodes)
+        .flat_map(|a| successors(pair(a, a)))
+        .filter(|node| !diagonal(*node))
+        .collect();
+    while let Some(node) = stack.pop() {
+        if seen[node] {
+            continue;
+        }
+        seen[node] = true;
+        for next in successors(node) {
+            if diagonal(next) {
+                return (false, false);
+            }
+            stack.push(next);
+        }
+    }
+
+    // Kahn's algorithm: off the diagonal, every pair can be peeled away unless some
+    // lie on a cycle
+    let off: Vec<usize> = (0..nodes * nodes).filter(|n| !diagonal(*n)).collect();
+    let mut incoming = vec![0; nodes * nodes];
+    for node in &off {
+        for next in successors(*node) {
+            if !diagonal(next) {
+                incoming[next] += 1;
+            }
+        }
+    }
+    let mut ready: Vec<usize> = off.iter().copied().filter(|n| incoming[*n] == 0).collect();
+    let mut peeled = 0;
+    while let Some(node) = ready.pop() {
+        peeled += 1;
+        for next in successors(node) {
+            if !diagonal(next) {
+                incoming[next] -= 1;
+                if incoming[next] == 0 {
+                    ready.push(next);
+                }
+            }
+        }
+    }
+    (true, peeled == off.len())
+}
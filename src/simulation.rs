@@ -0,0 +1,198 @@
+pub use crate::image_manip::bitmap::Boundary;
+
+use crate::image_manip::bitmap::BitMap;
+use crate::image_manip::init::Init;
+use crate::rule::Rule;
+
+// Snapshot of the lattice after index steps of the rule
+#[derive(Debug, Clone)]
+pub struct Generation {
+    pub index: u64,
+    pub cells: BitMap,
+}
+
+// Settings for a Simulation. The rule and either a width or an initial bitmap are
+// required, everything else has a default:
+//
+//     let simulation = Simulation::builder().rule(110).width(200).seed(42).build()?;
+//     for generation in simulation.take(100) { ... }
+#[derive(Debug, Clone)]
+pub struct SimulationBuilder {
+    rule: Option<Rule>,
+    width: Option<u64>,
+    seed: Option<u64>,
+    init: Init,
+    initial: Option<BitMap>,
+    boundary: Boundary,
+}
+
+impl SimulationBuilder {
+    pub fn rule<R: Into<Rule>>(mut self, rule: R) -> SimulationBuilder {
+        self.rule = Some(rule.into());
+        self
+    }
+
+    pub fn width(mut self, width: u64) -> SimulationBuilder {
+        self.width = Some(width);
+        self
+    }
+
+    // Seed for random initial conditions, so that equal seeds give equal runs
+    pub fn seed(mut self, seed: u64) -> SimulationBuilder {
+        self.seed = Some(seed);
+        self
+    }
+
+    // Generator of the initial bitmap, random with density 0.5 by default
+    pub fn init(mut self, init: Init) -> SimulationBuilder {
+        self.init = init;
+        self
+    }
+
+    // Starts from the given bitmap instead of generating one, taking the width from it
+    pub fn initial(mut self, cells: BitMap) -> SimulationBuilder {
+        self.initial = Some(cells);
+        self
+    }
+
+    pub fn boundary(mut self, boundary: Boundary) -> SimulationBuilder {
+        self.boundary = boundary;
+        self
+    }
+
+    pub fn build(self) -> Result<Simulation, String> {
+        let rule = match self.rule {
+            Some(rule) => rule,
+            None => return Err(String::from("Simulation needs a rule")),
+        };
+        let cells = match (self.initial, self.width) {
+            (Some(cells), Some(width)) if cells.size() as u64 != width => {
+                return Err(format!(
+                    "Initial bitmap has {} cells but the width is {}",
+                    cells.size(),
+                    width
+                ))
+            }
+            (Some(cells), _) => cells,
+            (None, Some(0)) => return Err(String::from("Width must be at least 1")),
+            (None, Some(width)) => self.init.build(width, self.seed)?,
+            (None, None) => {
+                return Err(String::from(
+                    "Simulation needs a width or an initial bitmap",
+                ))
+            }
+        };
+        Ok(Simulation::new(rule, self.boundary, cells))
+    }
+}
+
+// Elementary cellular automaton on a finite lattice. As an iterator it yields
+// generation 0, the initial bitmap, followed by every later generation in turn
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    rule: Rule,
+    boundary: Boundary,
+    cells: BitMap,
+    generation: u64,
+    // Whether generation has been yielded by next yet
+    yielded: bool,
+}
+
+impl Simulation {
+    pub fn builder() -> SimulationBuilder {
+        SimulationBuilder {
+            rule: None,
+            width: None,
+            seed: None,
+            init: Init::Random(0.5),
+            initial: None,
+            boundary: Boundary::Periodic,
+        }
+    }
+
+    pub fn new(rule: Rule, boundary: Boundary, cells: BitMap) -> Simulation {
+        Simulation {
+            rule,
+            boundary,
+            cells,
+            generation: 0,
+            yielded: false,
+        }
+    }
+
+    // Numbers the current cells as the given generation, e.g. to carry on a run from
+    // a checkpoint
+    pub fn starting_at(mut self, generation: u64) -> Simulation {
+        self.generation = generation;
+        self
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn boundary(&self) -> Boundary {
+        self.boundary
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.size()
+    }
+
+    // Index of the current generation
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn cells(&self) -> &BitMap {
+        &self.cells
+    }
+
+    // Advances to the next generation
+    pub fn step(&mut self) {
+        self.cells
+            .rule_step_with_boundary(self.rule.number(), self.boundary);
+        self.generation += 1;
+        self.yielded = false;
+    }
+
+    // Whether advance can jump straight to any later generation: the rule is additive
+    // and the lattice periodic
+    pub fn can_jump(&self) -> bool {
+        self.boundary == Boundary::Periodic && self.rule.properties().additive
+    }
+
+    // Advances by steps generations, jumping straight there when it can and stepping
+    // through each generation otherwise
+    pub fn advance(&mut self, steps: u64) {
+        if steps == 0 {
+            return;
+        }
+        if self.can_jump() {
+            self.cells
+                .additive_jump(self.rule.number(), steps)
+                .expect("additive rules can jump");
+            self.generation += steps;
+            self.yielded = false;
+        } else {
+            for _ in 0..steps {
+                self.step();
+            }
+        }
+    }
+}
+
+impl Iterator for Simulation {
+    type Item = Generation;
+
+    fn next(&mut self) -> Option<Generation> {
+        if self.yielded {
+            self.step();
+        }
+        self.yielded = true;
+        Some(Generation {
+            index: self.generation,
+            cells: self.cells.clone(),
+        })
+    }
+}
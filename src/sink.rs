@@ -0,0 +1,380 @@
+use crate::analysis::StatsStrip;
+use crate::cancel;
+use crate::image_manip::metadata::{Metadata, COMMENT_LABEL, PNG_TEXT_KEYWORD};
+use crate::output::{CountingWriter, SkipWriter};
+use crate::prog::Message;
+use crate::report::{CycleDetector, Stats};
+use crate::simulation::{Generation, Simulation};
+
+use gif::{AnyExtension, Encoder, EncodingError, Frame, Repeat};
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Receives every generation of a run in order. Cells are drawn with index 0 on the left
+pub trait Sink {
+    fn push(&mut self, generation: &Generation) -> Result<(), EncodingError>;
+
+    // Called once after the last generation, to flush anything buffered
+    fn finish(&mut self) -> Result<(), EncodingError> {
+        Ok(())
+    }
+}
+
+// Receives whole frames with one byte per pixel, 1 for occupied cells and 0 otherwise
+pub trait FrameSink {
+    fn push_frame(&mut self, width: u16, height: u16, pixels: &[u8]) -> Result<(), EncodingError>;
+
+    fn finish(&mut self) -> Result<(), EncodingError> {
+        Ok(())
+    }
+}
+
+// Turns generations into frames of height generations each, moving down by one
+// generation per frame, and passes them on to a FrameSink
+pub struct Window<F: FrameSink> {
+    sink: F,
+    width: u16,
+    height: u16,
+    pixels: Vec<u8>,
+    strip: Option<StatsStrip>,
+}
+
+impl<F: FrameSink> Window<F> {
+    pub fn new(sink: F, width: u16, height: u16) -> Window<F> {
+        Window {
+            sink,
+            width,
+            height,
+            pixels: Vec::with_capacity((width as usize + 1) * height as usize),
+            strip: None,
+        }
+    }
+
+    // Window of the dimensions of metadata, with the statistics strip it asks for
+    pub fn for_metadata(sink: F, metadata: &Metadata) -> Window<F> {
+        let window = Window::new(sink, metadata.width, metadata.height);
+        match metadata.strip {
+            Some(k) => window.with_strip(StatsStrip::new(k)),
+            None => window,
+        }
+    }
+
+    // Draws the statistics of each generation to its right
+    pub fn with_strip(mut self, strip: StatsStrip) -> Window<F> {
+        self.strip = Some(strip);
+        self
+    }
+
+    fn frame_width(&self) -> u16 {
+        StatsStrip::frame_width(self.width, self.strip.is_some())
+    }
+
+    // Number of generations needed to fill the given number of frames
+    pub fn generations_for(height: u16, frames: u32) -> u64 {
+        height as u64 + frames.max(1) as u64 - 1
+    }
+
+    pub fn into_inner(self) -> F {
+        self.sink
+    }
+}
+
+impl<F: FrameSink> Sink for Window<F> {
+    fn push(&mut self, generation: &Generation) -> Result<(), EncodingError> {
+        let frame_width = self.frame_width();
+        let num_pixels = frame_width as usize * self.height as usize;
+        if self.pixels.len() == num_pixels {
+            // delete first row
+            self.pixels.drain(0..frame_width.into());
+        }
+        let cells = match self.strip {
+            Some(_) => frame_width - StatsStrip::WIDTH,
+            None => frame_width,
+        };
+        self.pixels
+            .extend(generation.cells.to_bit_vec().iter().take(cells.into()));
+        if let Some(strip) = &self.strip {
+            self.pixels.extend(strip.draw(generation));
+        }
+        if self.pixels.len() == num_pixels {
+            self.sink
+                .push_frame(frame_width, self.height, &self.pixels)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), EncodingError> {
+        self.sink.finish()
+    }
+}
+
+// Looping GIF animation with the run's metadata in a comment
+pub struct GifSink<W: Write> {
+    encoder: Encoder<W>,
+}
+
+impl<W: Write> GifSink<W> {
+    pub fn new(writer: W, metadata: &Metadata) -> Result<GifSink<W>, EncodingError> {
+        // Set with two colours: white, black
+        let color_map = &[0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00];
+        let mut encoder = Encoder::new(writer, metadata.frame_width(), metadata.height, color_map)?;
+        encoder.set_repeat(Repeat::Infinite)?;
+        encoder
+            .write_raw_extension(AnyExtension(COMMENT_LABEL), &[metadata.encode().as_bytes()])?;
+        Ok(GifSink { encoder })
+    }
+
+    // Sink of generations for the animation described by metadata
+    pub fn animation(writer: W, metadata: &Metadata) -> Result<Window<GifSink<W>>, EncodingError> {
+        Ok(Window::for_metadata(
+            GifSink::new(writer, metadata)?,
+            metadata,
+        ))
+    }
+}
+
+impl<W: Write> GifSink<SkipWriter<W>> {
+    // Sink of frames to append to a GIF written by new with the same metadata, whose
+    // writer is positioned after its last frame. Leaves out the header
+    pub fn appending(
+        writer: W,
+        metadata: &Metadata,
+    ) -> Result<GifSink<SkipWriter<W>>, EncodingError> {
+        let header_len = Arc::new(AtomicU64::new(0));
+        let header = GifSink::new(
+            CountingWriter::new(io::sink(), header_len.clone()),
+            metadata,
+        )?;
+        // Read before dropping the sink, which writes the trailer
+        let skip = header_len.load(Ordering::Relaxed) as usize;
+        drop(header);
+        GifSink::new(SkipWriter::new(writer, skip), metadata)
+    }
+}
+
+impl<W: Write> FrameSink for GifSink<W> {
+    fn push_frame(&mut self, width: u16, height: u16, pixels: &[u8]) -> Result<(), EncodingError> {
+        let frame = Frame::from_indexed_pixels(width, height, pixels, None);
+        self.encoder.write_frame(&frame)
+    }
+}
+
+// Still PNG image with one row per generation, written once every generation is in
+pub struct PngSink<W: Write> {
+    writer: Option<W>,
+    metadata: Metadata,
+    rows: Vec<u8>,
+    height: u32,
+}
+
+impl<W: Write> PngSink<W> {
+    pub fn new(writer: W, metadata: &Metadata) -> PngSink<W> {
+        PngSink {
+            writer: Some(writer),
+            metadata: metadata.clone(),
+            rows: Vec::new(),
+            height: 0,
+        }
+    }
+}
+
+impl<W: Write> Sink for PngSink<W> {
+    fn push(&mut self, generation: &Generation) -> Result<(), EncodingError> {
+        // White for empty cells, black for occupied ones
+        self.rows.extend(
+            generation
+                .cells
+                .to_bit_vec()
+                .iter()
+                .take(self.metadata.width.into())
+                .map(|c| if *c == 1 { 0x00 } else { 0xFF }),
+        );
+        self.height += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), EncodingError> {
+        let writer = match self.writer.take() {
+            Some(writer) => writer,
+            None => return Ok(()),
+        };
+        let mut encoder = png::Encoder::new(writer, self.metadata.width.into(), self.height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::from)?;
+        let mut text = PNG_TEXT_KEYWORD.as_bytes().to_vec();
+        text.push(0);
+        text.extend(self.metadata.encode().as_bytes());
+        writer
+            .write_chunk(*b"tEXt", &text)
+            .map_err(io::Error::from)?;
+        writer
+            .write_image_data(&self.rows)
+            .map_err(io::Error::from)?;
+        Ok(())
+    }
+}
+
+// Text with one line per generation, e.g. for printing to a terminal
+pub struct TerminalSink<W: Write> {
+    writer: W,
+    pub occupied: char,
+    pub empty: char,
+}
+
+impl<W: Write> TerminalSink<W> {
+    pub fn new(writer: W) -> TerminalSink<W> {
+        TerminalSink {
+            writer,
+            occupied: '█',
+            empty: ' ',
+        }
+    }
+}
+
+impl<W: Write> Sink for TerminalSink<W> {
+    fn push(&mut self, generation: &Generation) -> Result<(), EncodingError> {
+        let line: String = generation
+            .cells
+            .to_bit_vec()
+            .iter()
+            .map(|c| if *c == 1 { self.occupied } else { self.empty })
+            .collect();
+        writeln!(self.writer, "{}", line)?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), EncodingError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// Keeps everything it receives in memory, one byte per cell or pixel
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemorySink {
+    pub generations: Vec<Vec<u8>>,
+    pub frames: Vec<Vec<u8>>,
+}
+
+impl MemorySink {
+    pub fn new() -> MemorySink {
+        MemorySink::default()
+    }
+}
+
+impl Sink for MemorySink {
+    fn push(&mut self, generation: &Generation) -> Result<(), EncodingError> {
+        self.generations.push(generation.cells.to_bit_vec());
+        Ok(())
+    }
+}
+
+impl FrameSink for MemorySink {
+    fn push_frame(
+        &mut self,
+        _width: u16,
+        _height: u16,
+        pixels: &[u8],
+    ) -> Result<(), EncodingError> {
+        self.frames.push(pixels.to_vec());
+        Ok(())
+    }
+}
+
+// Generations stepped through between updates of the progress bar while skipping
+const SKIP_UPDATE: u64 = 1024;
+
+// Advances simulation by steps generations without drawing them, jumping straight there
+// when it can. Sends the number skipped so far to the progress bar, if any, and stops
+// early if the run is cancelled
+pub fn skip(
+    simulation: &mut Simulation,
+    steps: u64,
+    progress_bar_tx_wrap: Option<&Sender<Message>>,
+) {
+    let update = |done: u64| {
+        if let Some(progress_bar_tx) = progress_bar_tx_wrap {
+            progress_bar_tx.send(Message::Skipped(done)).unwrap();
+        }
+    };
+    if simulation.can_jump() {
+        simulation.advance(steps);
+    } else {
+        let mut done = 0;
+        while done < steps {
+            // Steps SKIP_UPDATE generations between updates
+            let chunk = SKIP_UPDATE.min(steps - done);
+            for _ in 0..chunk {
+                if cancel::is_cancelled() {
+                    return;
+                }
+                simulation.step();
+            }
+            done += chunk;
+            if done < steps {
+                update(done);
+            }
+        }
+    }
+    update(steps);
+}
+
+// Feeds every generation into sink, timing the simulation separately from the sink and
+// looking for cycles. Stops early if the run is cancelled. Sends the number of generations done
+// and the time spent on each phase to the progress bar, if any
+pub fn drive<I: Iterator<Item = Generation>, S: Sink + ?Sized>(
+    mut generations: I,
+    sink: &mut S,
+    progress_bar_tx_wrap: Option<&Sender<Message>>,
+) -> Result<Stats, EncodingError> {
+    let mut simulation_time = Duration::new(0, 0);
+    let mut cycles = CycleDetector::new();
+    let mut final_density = 0.0;
+    let started = Instant::now();
+
+    let mut done = 0;
+    // Stopping early still leaves a valid, shorter output
+    while !cancel::is_cancelled() {
+        let simulation_started = Instant::now();
+        let generation = match generations.next() {
+            Some(generation) => generation,
+            None => break,
+        };
+        let row = generation.cells.to_bit_vec();
+        cycles.observe(generation.index, &row);
+        final_density = row.iter().filter(|c| **c == 1).count() as f64 / row.len() as f64;
+        simulation_time += simulation_started.elapsed();
+
+        sink.push(&generation)?;
+        if let Some(progress_bar_tx) = progress_bar_tx_wrap {
+            progress_bar_tx.send(Message::Update(done)).unwrap();
+            progress_bar_tx
+                .send(Message::Timing {
+                    simulation: simulation_time,
+                    encoding: started.elapsed() - simulation_time,
+                })
+                .unwrap();
+        }
+        done += 1;
+    }
+    if let Some(progress_bar_tx) = progress_bar_tx_wrap {
+        progress_bar_tx.send(Message::Encoding).unwrap();
+    }
+    sink.finish()?;
+    // Finish updating progress bar
+    if let Some(progress_bar_tx) = progress_bar_tx_wrap {
+        progress_bar_tx.send(Message::Kill).unwrap();
+    }
+
+    Ok(Stats {
+        simulation_time,
+        encoding_time: started.elapsed() - simulation_time,
+        final_density,
+        cycle: cycles.cycle(),
+    })
+}
@@ -0,0 +1,324 @@
+use crate::cancel;
+use crate::image_manip::bitmap::{BitMap, Boundary};
+use crate::image_manip::init::Init;
+use crate::image_manip::metadata::Metadata;
+use crate::image_manip::{is_still, render_to_writer};
+use crate::output::AtomicFile;
+use crate::prog::Message;
+use crate::report::{Report, Reporting, Stats};
+use crate::rule::Rule;
+use crate::simulation::Simulation;
+
+use gif::EncodingError;
+
+use std::fs::File;
+use std::io;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+use std::thread;
+
+// Single point in a parameter sweep
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepRun {
+    pub rule: u8,
+    pub density: f64,
+    pub seed: Option<u64>,
+}
+
+pub struct Sweep {
+    pub rules: Vec<u8>,
+    pub densities: Vec<f64>,
+    // Empty when no seed was given, in which case every run is randomly seeded
+    pub seeds: Vec<u64>,
+    pub template: String,
+    pub threads: usize,
+    pub boundary: Boundary,
+    pub reporting: Reporting,
+}
+
+impl Sweep {
+    // Expands the rule, density and seed lists into their cartesian product
+    pub fn runs(&self) -> Vec<SweepRun> {
+        let seeds: Vec<Option<u64>> = if self.seeds.is_empty() {
+            vec![None]
+        } else {
+            self.seeds.iter().map(|s| Some(*s)).collect()
+        };
+        let mut runs = Vec::with_capacity(self.rules.len() * self.densities.len() * seeds.len());
+        for rule in &self.rules {
+            for density in &self.densities {
+                for seed in &seeds {
+                    runs.push(SweepRun {
+                        rule: *rule,
+                        density: *density,
+                        seed: *seed,
+                    });
+                }
+            }
+        }
+        runs
+    }
+
+    // Template used when no output is given: the single-run default name, with
+    // a field added for each of density and seed when they take more than one value
+    pub fn default_template(densities: &[f64], seeds: &[u64]) -> String {
+        let mut template = String::from("output_w{width}_h{height}_f{frames}_r{rule}");
+        if densities.len() > 1 {
+            template.push_str("_d{density}");
+        }
+        if seeds.len() > 1 {
+            template.push_str("_s{seed}");
+        }
+        template.push_str(".gif");
+        template
+    }
+
+    // Every run must get its own file, so the template has to mention each varying field
+    pub fn validate_template(&self) -> Result<(), String> {
+        let varying = [
+            ("{rule}", self.rules.len() > 1),
+            ("{density}", self.densities.len() > 1),
+            ("{seed}", self.seeds.len() > 1),
+        ];
+        for (field, varies) in varying.iter() {
+            if *varies && !self.template.contains(field) {
+                return Err(format!(
+                    "Output template '{}' must contain {} since it takes more than one value",
+                    self.template, field
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Replaces {rule}, {density}, {seed}, {width}, {height} and {frames} in template
+pub fn format_output(
+    template: &str,
+    run: &SweepRun,
+    width: u16,
+    height: u16,
+    frames: u32,
+) -> String {
+    let seed = match run.seed {
+        Some(seed) => seed.to_string(),
+        None => String::from("random"),
+    };
+    template
+        .replace("{rule}", &run.rule.to_string())
+        .replace("{density}", &run.density.to_string())
+        .replace("{seed}", &seed)
+        .replace("{width}", &width.to_string())
+        .replace("{height}", &height.to_string())
+        .replace("{frames}", &frames.to_string())
+}
+
+// Parses a comma separated list of values and inclusive ranges of integers no
+// greater than max, e.g. "30,90,100..110" or "0..255:5"
+pub fn parse_int_list(spec: &str, max: u64) -> Result<Vec<u64>, String> {
+    let mut values = Vec::new();
+    for item in spec.split(',').map(str::trim) {
+        match split_range(item)? {
+            Some((start, end, step)) => {
+                let start: u64 = parse_value(start)?;
+                let end: u64 = parse_value(end)?;
+                let step: u64 = match step {
+                    Some(step) => parse_value(step)?,
+                    None => 1,
+                };
+                if step == 0 {
+                    return Err(format!("Step in '{}' must be positive", item));
+                }
+                if start > end {
+                    return Err(format!("Range '{}' is empty", item));
+                }
+                let mut value = start;
+                while value <= end {
+                    values.push(value);
+                    value = match value.checked_add(step) {
+                        Some(v) => v,
+                        None => break,
+                    };
+                }
+            }
+            None => values.push(parse_value(item)?),
+        }
+    }
+    match values.iter().find(|v| **v > max) {
+        Some(v) => Err(format!("{} is out of range (0-{})", v, max)),
+        None => Ok(values),
+    }
+}
+
+// Parses a comma separated list of values and inclusive ranges of floats,
+// e.g. "0.1..0.9:0.1"
+pub fn parse_float_list(spec: &str) -> Result<Vec<f64>, String> {
+    let mut values = Vec::new();
+    for item in spec.split(',').map(str::trim) {
+        match split_range(item)? {
+            Some((start, end, step)) => {
+                let start: f64 = parse_value(start)?;
+                let end: f64 = parse_value(end)?;
+                let step: f64 = match step {
+                    Some(step) => parse_value(step)?,
+                    None => {
+                        return Err(format!("Range '{}' needs a step, e.g. {}:0.1", item, item))
+                    }
+                };
+                if step <= 0.0 {
+                    return Err(format!("Step in '{}' must be positive", item));
+                }
+                if start > end {
+                    return Err(format!("Range '{}' is empty", item));
+                }
+                // Count steps up front so that rounding errors cannot add or drop the endpoint
+                let num_steps = ((end - start) / step + 1e-9).floor() as u64;
+                for i in 0..=num_steps {
+                    let value = start + (i as f64) * step;
+                    values.push((value * 1e9).round() / 1e9);
+                }
+            }
+            None => values.push(parse_value(item)?),
+        }
+    }
+    Ok(values)
+}
+
+// Start, end and optional step of a range
+type RangeParts<'a> = (&'a str, &'a str, Option<&'a str>);
+
+// Splits "start..end[:step]" (or "start..=end[:step]") into its parts
+fn split_range(item: &str) -> Result<Option<RangeParts<'_>>, String> {
+    let (start, rest) = match item.find("..") {
+        Some(i) => (&item[..i], &item[i + 2..]),
+        None => return Ok(None),
+    };
+    let rest = rest.strip_prefix('=').unwrap_or(rest);
+    let (end, step) = match rest.find(':') {
+        Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+        None => (rest, None),
+    };
+    if start.is_empty() || end.is_empty() {
+        return Err(format!("Could not interpret range '{}'", item));
+    }
+    Ok(Some((start, end, step)))
+}
+
+fn parse_value<T: FromStr>(value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("Could not interpret '{}'", value))
+}
+
+// Builds every run of the sweep, spreading them over sweep.threads worker threads.
+// If init_line is given it seeds every run, otherwise each run builds its bitmap from
+// init with its seed, or a random bitmap from its density and seed if there is no
+// init. Sends the number of finished runs to progress_bar_tx_wrap and returns the
+// output files that could not be built.
+pub fn run_sweep(
+    sweep: &Sweep,
+    width: u16,
+    height: u16,
+    steps: u32,
+    init_line: Option<&BitMap>,
+    init: Option<&Init>,
+    progress_bar_tx_wrap: Option<&Sender<Message>>,
+) -> Vec<(String, EncodingError)> {
+    let runs = sweep.runs();
+    let next_run = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+    let failures = Mutex::new(Vec::new());
+    let progress_bar_tx = progress_bar_tx_wrap.map(|tx| Mutex::new(tx.clone()));
+
+    thread::scope(|scope| {
+        for _ in 0..sweep.threads.max(1) {
+            scope.spawn(|| loop {
+                let i = next_run.fetch_add(1, Ordering::SeqCst);
+                if i >= runs.len() || cancel::is_cancelled() {
+                    return;
+                }
+                let run = &runs[i];
+                let output = format_output(&sweep.template, run, width, height, steps);
+                let built = match (init_line, init) {
+                    (Some(line), _) => Ok(line.clone()),
+                    (None, Some(init)) => init.build(width.into(), run.seed),
+                    (None, None) => Init::Random(run.density).build(width.into(), run.seed),
+                };
+                let result = match built {
+                    Ok(line) => {
+                        let mut metadata = Metadata::new(run.rule, width, height, steps, &line);
+                        metadata.boundary = sweep.boundary;
+                        if is_still(&output) {
+                            metadata.frames = 1;
+                        }
+                        if init_line.is_none() {
+                            metadata.seed = run.seed;
+                            match init {
+                                Some(init) => metadata.init = Some(init.to_string()),
+                                None => metadata.density = Some(run.density),
+                            }
+                        }
+                        write_run(sweep, &output, &metadata, line)
+                    }
+                    Err(e) => Err(io::Error::new(io::ErrorKind::InvalidInput, e).into()),
+                };
+                if let Err(e) = result {
+                    failures.lock().unwrap().push((output, e));
+                }
+                let done = finished.fetch_add(1, Ordering::SeqCst) as u32;
+                if let Some(tx) = &progress_bar_tx {
+                    tx.lock().unwrap().send(Message::Update(done)).unwrap();
+                }
+            });
+        }
+    });
+
+    if let Some(tx) = progress_bar_tx_wrap {
+        tx.send(Message::Kill).unwrap();
+    }
+    failures.into_inner().unwrap()
+}
+
+// Builds one run into a temporary file and moves it into place, unless the sweep was
+// cancelled in the meantime. Runs cut short are discarded rather than left looking
+// complete. Outputs ending in .png are still images, as for single runs
+fn write_run(
+    sweep: &Sweep,
+    output: &str,
+    metadata: &Metadata,
+    line: BitMap,
+) -> Result<(), EncodingError> {
+    // Existing outputs were checked before the sweep started
+    let file = AtomicFile::new(output, true)?;
+    let simulation = Simulation::new(Rule::new(metadata.rule), metadata.boundary, line);
+    let writer = File::create(file.temp_name())?;
+    let stats = render_to_writer(simulation, metadata, writer, is_still(output), None)?;
+    if cancel::is_cancelled() {
+        return Ok(());
+    }
+    file.commit()?;
+    if sweep.reporting.enabled() {
+        report_run(&sweep.reporting, output, metadata, &stats)?;
+    }
+    Ok(())
+}
+
+// Prints and/or writes the report of a finished run
+pub fn report_run(
+    reporting: &Reporting,
+    output: &str,
+    metadata: &Metadata,
+    stats: &Stats,
+) -> Result<(), EncodingError> {
+    let report = Report::new(output, metadata, stats)?;
+    if reporting.sidecar {
+        report.write_sidecar()?;
+    }
+    if reporting.stdout {
+        println!("{}", report.to_json());
+    }
+    Ok(())
+}
@@ -0,0 +1,184 @@
+use crate::image_manip::bitmap::{BitMap, Boundary};
+use crate::simulation::Generation;
+
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+// How the window of cells drawn moves from one generation to the next
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Track {
+    // The window stays where it started
+    Fixed,
+    // The window is centred on the centre of mass of the occupied cells
+    Mass,
+    // The window moves this many cells a generation, towards higher indices if positive
+    Velocity(f64),
+}
+
+impl FromStr for Track {
+    type Err = String;
+
+    // fixed, mass, or a velocity such as 0.5, -2 or 2/3
+    fn from_str(s: &str) -> Result<Track, String> {
+        let invalid = || {
+            format!(
+                "Could not interpret '{}' as fixed, mass or a velocity such as 2/3",
+                s
+            )
+        };
+        match s {
+            "fixed" => return Ok(Track::Fixed),
+            "mass" => return Ok(Track::Mass),
+            _ => {}
+        }
+        let velocity = match s.split_once('/') {
+            Some((p, q)) => {
+                let (p, q): (f64, f64) = (
+                    p.parse().map_err(|_| invalid())?,
+                    q.parse().map_err(|_| invalid())?,
+                );
+                if q == 0.0 {
+                    return Err(invalid());
+                }
+                p / q
+            }
+            None => s.parse().map_err(|_| invalid())?,
+        };
+        if !f64::is_finite(velocity) {
+            return Err(invalid());
+        }
+        Ok(Track::Velocity(velocity))
+    }
+}
+
+impl fmt::Display for Track {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Track::Fixed => write!(f, "fixed"),
+            Track::Mass => write!(f, "mass"),
+            Track::Velocity(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+// Window of cells drawn from a lattice that may be much wider, or unbounded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    // Leftmost cell drawn at the first generation
+    pub x0: i64,
+    pub width: u16,
+    pub track: Track,
+}
+
+impl Viewport {
+    pub fn fixed(x0: i64, width: u16) -> Viewport {
+        Viewport {
+            x0,
+            width,
+            track: Track::Fixed,
+        }
+    }
+
+    // Leftmost cell that centres a window of width cells on a lattice of lattice_width
+    pub fn centred(lattice_width: usize, width: u16) -> i64 {
+        (lattice_width as i64 - i64::from(width)) / 2
+    }
+
+    // Leftmost cell drawn elapsed generations after the first, given the centre of mass
+    // of the occupied cells if the window tracks it, and the leftmost cell drawn before
+    pub fn left(&self, elapsed: u64, centre: Option<f64>, previous: i64) -> i64 {
+        match self.track {
+            Track::Fixed => self.x0,
+            Track::Velocity(v) => self.x0 + (v * elapsed as f64).round() as i64,
+            // Nothing to follow once every cell is empty
+            Track::Mass => centre.map_or(previous, |c| {
+                (c - (f64::from(self.width) - 1.0) / 2.0).round() as i64
+            }),
+        }
+    }
+}
+
+// Centre of mass of the occupied cells of a finite lattice, if there are any. Periodic
+// lattices have no ends, so their cells are taken as points on a circle, whose mean
+// direction gives the centre. It is undefined when the cells balance out around it
+pub fn centre_of_mass(cells: &BitMap, boundary: Boundary) -> Option<f64> {
+    let n = cells.size();
+    let occupied = (0..n).filter(|i| cells.get(*i) == 1);
+    if boundary != Boundary::Periodic {
+        let (count, sum) = occupied.fold((0, 0.0), |(c, s), i| (c + 1, s + i as f64));
+        return if count == 0 {
+            None
+        } else {
+            Some(sum / count as f64)
+        };
+    }
+    let (x, y) = occupied
+        .map(|i| 2.0 * PI * i as f64 / n as f64)
+        .fold((0.0, 0.0), |(x, y), a| (x + a.cos(), y + a.sin()));
+    if x.hypot(y) < 1e-9 {
+        return None;
+    }
+    Some(y.atan2(x).rem_euclid(2.0 * PI) * n as f64 / (2.0 * PI))
+}
+
+// Generations of a finite lattice cut down to the cells of a viewport. Windows that
+// cross an edge of a periodic lattice wrap around it, others are kept inside
+#[derive(Debug, Clone)]
+pub struct Cropped<I: Iterator<Item = Generation>> {
+    generations: I,
+    viewport: Viewport,
+    boundary: Boundary,
+    // Index of the first generation, and the leftmost cell drawn last
+    first: Option<u64>,
+    left: i64,
+}
+
+impl<I: Iterator<Item = Generation>> Cropped<I> {
+    pub fn new(generations: I, viewport: Viewport, boundary: Boundary) -> Cropped<I> {
+        Cropped {
+            generations,
+            viewport,
+            boundary,
+            first: None,
+            left: viewport.x0,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Generation>> Iterator for Cropped<I> {
+    type Item = Generation;
+
+    fn next(&mut self) -> Option<Generation> {
+        let generation = self.generations.next()?;
+        let first = *self.first.get_or_insert(generation.index);
+        let centre = match self.viewport.track {
+            Track::Mass => centre_of_mass(&generation.cells, self.boundary),
+            _ => None,
+        };
+        let n = generation.cells.size() as i64;
+        let width = self.viewport.width;
+        let periodic = self.boundary == Boundary::Periodic;
+        let mut left = self
+            .viewport
+            .left(generation.index - first, centre, self.left);
+        left = if periodic {
+            left.rem_euclid(n.max(1))
+        } else {
+            left.clamp(0, (n - i64::from(width)).max(0))
+        };
+        self.left = left;
+        let cells = BitMap::from_fn(width.into(), |i| {
+            let i = if periodic {
+                (left + i as i64) % n
+            } else {
+                left + i as i64
+            };
+            i < n && generation.cells.get(i as usize) == 1
+        });
+        Some(Generation {
+            index: generation.index,
+            cells,
+        })
+    }
+}
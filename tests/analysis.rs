@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod analysis_tests {
+    use cellular::analysis::*;
+    use cellular::image_manip::bitmap::BitMap;
+    use cellular::simulation::{Boundary, Simulation};
+    use cellular::sink::{drive, MemorySink, Sink, Window};
+
+    #[test]
+    fn test_measure() {
+        let stats = GenerationStats::from_cells(3, &[1, 1, 0, 0], 2);
+        assert!(stats.generation == 3);
+        assert!(stats.density == 0.5);
+        assert!(stats.runs == 1);
+        // Every block of 2 cells appears once around the lattice
+        assert!(stats.block_entropies == vec![1.0, 2.0]);
+        assert!(stats.autocorrelation == vec![Some(0.0), Some(-1.0)]);
+
+        let full = GenerationStats::from_cells(0, &[1, 1, 1], 2);
+        assert!(full.runs == 1);
+        assert!(full.block_entropies == vec![0.0, 0.0]);
+        assert!(full.autocorrelation == vec![None, None]);
+
+        let runs = GenerationStats::from_cells(0, &[1, 0, 1, 1, 0, 1], 1);
+        // The first and last cells are one run around the edge
+        assert!(runs.runs == 2);
+    }
+
+    #[test]
+    fn test_stats_sink() {
+        let simulation = Simulation::new(90.into(), Boundary::Periodic, BitMap::single(8));
+        let mut csv = Vec::new();
+        let mut sink = StatsSink::new(&mut csv, StatsFormat::Csv, 2);
+        drive(simulation.clone().take(3), &mut sink, None).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines.len() == 4);
+        assert!(lines[0] == GenerationStats::csv_header(2));
+        assert!(lines[1].starts_with("0,0.125,1,"));
+
+        let mut json = Vec::new();
+        let mut sink = StatsSink::new(&mut json, StatsFormat::Json, 2);
+        let recorded: Vec<_> = sink.recording(simulation.take(2)).collect();
+        sink.finish().unwrap();
+        assert!(recorded.len() == 2);
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.lines().count() == 2);
+        assert!(json.starts_with("{\"generation\":0,\"density\":0.125,\"runs\":1,"));
+        assert!(StatsFormat::from_path("run.jsonl") == StatsFormat::Json);
+        assert!(StatsFormat::from_path("run.csv") == StatsFormat::Csv);
+    }
+
+    #[test]
+    fn test_window_strip() {
+        let simulation = Simulation::new(90.into(), Boundary::Periodic, BitMap::single(8));
+        let mut window = Window::new(MemorySink::new(), 8, 2).with_strip(StatsStrip::new(2));
+        drive(simulation.take(3), &mut window, None).unwrap();
+        let memory = window.into_inner();
+        let frame_width = 8 + StatsStrip::WIDTH as usize;
+        assert!(memory.frames.len() == 2);
+        assert!(memory.frames[0].len() == frame_width * 2);
+        // One occupied cell in 8 fills an eighth of the density bar, after the gap
+        let strip = &memory.frames[0][8..frame_width];
+        assert!(strip[..2] == [0, 0]);
+        assert!(strip[2..5] == [1, 1, 1]);
+        assert!(strip[5] == 0);
+
+        // Frames never overflow, however wide the lattice
+        assert!(StatsStrip::frame_width(8, true) as usize == frame_width);
+        assert!(StatsStrip::frame_width(8, false) == 8);
+        assert!(StatsStrip::frame_width(StatsStrip::MAX_CELLS, true) == u16::MAX);
+        assert!(StatsStrip::frame_width(u16::MAX, true) == u16::MAX);
+    }
+}
@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod atlas_tests {
+    use cellular::atlas::*;
+    use cellular::image_manip::bitmap::BitMap;
+    use cellular::prog::ProgBar;
+    use std::fs::{self, File};
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn test_atlas_dimensions() {
+        let atlas = Atlas {
+            rules: (0..=255).collect(),
+            columns: 16,
+        };
+        // 16 tiles of 24 pixels plus 17 gaps of 2, labels are 14 pixels high
+        assert!(atlas.dimensions(24, 30) == (16 * 24 + 17 * 2, 16 * (30 + 14) + 17 * 2));
+
+        let atlas = Atlas {
+            rules: vec![30, 90, 110],
+            columns: 16,
+        };
+        // Columns are capped at the number of rules
+        assert!(atlas.dimensions(24, 30) == (3 * 24 + 4 * 2, 30 + 14 + 2 * 2));
+    }
+
+    #[test]
+    fn test_build_atlas() {
+        let atlas = Atlas {
+            rules: vec![30, 90, 110, 150, 184],
+            columns: 2,
+        };
+        let file_name = "test_atlas.gif";
+        let bmp = BitMap::random_seeded(20, 0.5, 7);
+        build_atlas(&atlas, 20, 10, 3, &bmp, file_name, None).unwrap();
+
+        let mut gif_opts = gif::DecodeOptions::new();
+        gif_opts.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = gif_opts.read_info(File::open(file_name).unwrap()).unwrap();
+        let (width, height) = atlas.dimensions(20, 10);
+        assert!(decoder.width() as usize == width);
+        assert!(decoder.height() as usize == height);
+        let mut frames = 0;
+        while decoder.read_next_frame().unwrap().is_some() {
+            frames += 1;
+        }
+        assert!(frames == 3);
+        fs::remove_file(file_name).unwrap();
+    }
+
+    #[test]
+    fn test_still_atlas_progress() {
+        let atlas = Atlas {
+            rules: vec![30, 90],
+            columns: 2,
+        };
+        let file_name = "test_atlas_progress.png";
+        let (progress_tx, progress_rx) = mpsc::channel();
+        let progress_bar = ProgBar::new(&String::from(file_name), 1);
+        let progress_thread = thread::spawn(move || progress_bar.run(progress_rx));
+        let bmp = BitMap::random_seeded(20, 0.5, 7);
+        build_atlas(&atlas, 20, 10, 3, &bmp, file_name, Some(&progress_tx)).unwrap();
+        // A still atlas is one frame, which fills the bar
+        let progress_bar = progress_thread.join().unwrap();
+        assert!(progress_bar.position() == progress_bar.length());
+        assert!(fs::read(file_name).unwrap().starts_with(b"\x89PNG"));
+        fs::remove_file(file_name).unwrap();
+    }
+}
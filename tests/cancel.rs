@@ -0,0 +1,22 @@
+#[cfg(test)]
+mod cancel_tests {
+    use cellular::cancel;
+    use cellular::image_manip::bitmap::BitMap;
+    use cellular::simulation::{Boundary, Simulation};
+    use cellular::sink::{drive, MemorySink};
+
+    // Cancellation is process wide, so this is the only test in its binary
+    #[test]
+    fn test_cancel_stops_drive() {
+        let simulation = Simulation::new(110.into(), Boundary::Periodic, BitMap::single(20));
+        let mut memory = MemorySink::new();
+        drive(simulation.clone().take(3), &mut memory, None).unwrap();
+        assert!(memory.generations.len() == 3);
+
+        cancel::cancel();
+        assert!(cancel::is_cancelled());
+        let mut memory = MemorySink::new();
+        drive(simulation, &mut memory, None).unwrap();
+        assert!(memory.generations.is_empty());
+    }
+}
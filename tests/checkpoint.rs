@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod checkpoint_tests {
+    use cellular::checkpoint::*;
+    use cellular::image_manip::bitmap::BitMap;
+    use cellular::image_manip::metadata::Metadata;
+    use cellular::image_manip::render_gif_to_writer;
+    use cellular::output::CountingWriter;
+    use cellular::sink::{GifSink, Window};
+    use std::fs;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::Arc;
+
+    fn metadata() -> Metadata {
+        let init_line = BitMap::from_bit_string("0001011001101000111010");
+        Metadata::new(110, 22, 4, 10, &init_line)
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let mut checkpoint = Checkpoint::new(&metadata(), "out.gif", ".partial.gif", 5);
+        checkpoint.frames_done = 5;
+        checkpoint.bytes = 1234;
+        checkpoint.generation = 5;
+        checkpoint.cells = String::from("0110011001100110011001");
+        let text = checkpoint.encode();
+        assert!(Checkpoint::decode(&text) == Ok(checkpoint));
+        assert!(Checkpoint::decode(&text.replace("every=5", "every=0")).is_err());
+        assert!(Checkpoint::decode(&metadata().encode()).is_err());
+    }
+
+    #[test]
+    fn test_segment_metadata() {
+        let mut metadata = metadata();
+        metadata.strip = Some(3);
+        let mut checkpoint = Checkpoint::new(&metadata, "out.gif", ".partial.gif", 5);
+        checkpoint.frames_done = 5;
+        let segment = checkpoint.segment_metadata();
+        assert!(segment.frames == 5);
+        assert!(segment.strip == Some(3));
+        assert!(segment.frame_width() == metadata.frame_width());
+    }
+
+    #[test]
+    fn test_resume_matches_full_run() {
+        // Runs that start later count their frames from the start generation
+        for start in [0, 40] {
+            let mut metadata = metadata();
+            metadata.start = start;
+            let mut full = Vec::new();
+            render_gif_to_writer(metadata.simulation(), &metadata, &mut full, None).unwrap();
+
+            // Stop after frame 7, with a checkpoint every 3 frames
+            let output = std::env::temp_dir().join("cellular_checkpoint_test.gif");
+            let output = output.to_str().unwrap();
+            let mut partial = Vec::new();
+            let bytes = Arc::new(AtomicU64::new(0));
+            render_checkpointed(
+                metadata.simulation().take(4 + 7 - 1),
+                GifSink::animation(CountingWriter::new(&mut partial, bytes.clone()), &metadata)
+                    .unwrap(),
+                Checkpoint::new(&metadata, output, "", 3),
+                bytes,
+                None,
+            )
+            .unwrap();
+            let checkpoint = Checkpoint::read(&Checkpoint::path(output)).unwrap();
+            assert!(checkpoint.frames_done == 6);
+            assert!(checkpoint.generation == start + 6);
+
+            partial.truncate(checkpoint.bytes as usize);
+            let bytes = Arc::new(AtomicU64::new(checkpoint.bytes));
+            let sink = GifSink::appending(&mut partial, &metadata).unwrap();
+            render_checkpointed(
+                checkpoint
+                    .simulation()
+                    .take(checkpoint.generations_left() as usize),
+                Window::new(sink, metadata.width, metadata.height),
+                checkpoint.clone(),
+                bytes,
+                None,
+            )
+            .unwrap();
+            assert!(partial == full);
+
+            remove(output).unwrap();
+            assert!(fs::metadata(Checkpoint::path(output)).is_err());
+        }
+    }
+}
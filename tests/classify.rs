@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod classify_tests {
+    use cellular::classify::*;
+    use cellular::image_manip::bitmap::BitMap;
+
+    #[test]
+    fn test_measures() {
+        let constant = vec![0; 10000];
+        let alternating: Vec<u8> = (0..10000).map(|i| (i % 2) as u8).collect();
+        let noise = BitMap::random_seeded(10000, 0.5, 1).to_bit_vec();
+        assert!(lz_complexity(&constant) < lz_complexity(&alternating));
+        assert!(lz_complexity(&alternating) < 0.25 * lz_complexity(&noise));
+
+        // Every row repeats the one before shifted by a cell
+        let rows: Vec<Vec<u8>> = (0..20)
+            .map(|t| (0..10).map(|x| ((x + t) % 3 == 0) as u8).collect())
+            .collect();
+        assert!(regularity(&rows) == 1.0);
+        assert!(regularity(&vec![vec![1; 10]; 20]) == 1.0);
+    }
+
+    #[test]
+    fn test_textbook_classes() {
+        // Smaller than the default so that all 256 rules run quickly, but still large
+        // enough that chaotic rules do not cycle
+        let classifier = Classifier {
+            width: 99,
+            generations: 300,
+            density: 0.5,
+            seeds: vec![1, 2, 3],
+        };
+        let classes: Vec<WolframClass> = (0..=255)
+            .map(|rule| classifier.classify(rule).class)
+            .collect();
+        let textbook = [
+            (
+                WolframClass::Uniform,
+                vec![0, 8, 32, 40, 128, 136, 160, 168, 255],
+            ),
+            (
+                WolframClass::Periodic,
+                vec![1, 4, 50, 108, 170, 184, 204, 232],
+            ),
+            (
+                WolframClass::Chaotic,
+                vec![18, 22, 30, 45, 90, 105, 122, 126, 146, 150],
+            ),
+            (WolframClass::Complex, vec![54, 110, 124, 137, 193]),
+        ];
+        for (class, rules) in textbook.iter() {
+            for rule in rules {
+                assert!(
+                    classes[*rule] == *class,
+                    "rule {} is not class {}",
+                    rule,
+                    class
+                );
+            }
+        }
+
+        let rule_110 = classifier.classify(110);
+        assert!(rule_110.confidence == 1.0);
+        assert!(rule_110.evidence.cycled == 0.0 && rule_110.evidence.damage > 0.25);
+        assert!(rule_110
+            .to_json()
+            .starts_with("{\"rule\":110,\"class\":\"IV\","));
+    }
+}
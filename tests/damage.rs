@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod damage_tests {
+    use cellular::damage::*;
+    use cellular::image_manip::bitmap::BitMap;
+    use cellular::simulation::{Boundary, Simulation};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn empty(rule: u8) -> Simulation {
+        Simulation::new(rule.into(), Boundary::Periodic, BitMap::new(21))
+    }
+
+    #[test]
+    fn test_damage_fronts() {
+        let generations: Vec<DamageGeneration> =
+            Damage::new(empty(90), 10).unwrap().take(4).collect();
+        assert!(generations[0].hamming == 1);
+        assert!(generations[0].left == Some(0) && generations[0].right == Some(0));
+        // Rule 90 spreads damage one cell a generation each way
+        assert!(generations[1].hamming == 2);
+        assert!(generations[3].left == Some(3) && generations[3].right == Some(3));
+        assert!(generations[3].front_speeds() == Some((1.0, 1.0)));
+        assert!(generations[3].to_csv() == "3,4,3,3");
+
+        // Fronts are timed from the generation the cell is flipped at
+        let later: Vec<DamageGeneration> = Damage::new(empty(90).starting_at(100), 10)
+            .unwrap()
+            .take(4)
+            .collect();
+        assert!(later[3].index == 103 && later[3].start == 100);
+        assert!(later[3].front_speeds() == Some((1.0, 1.0)));
+
+        let died: Vec<DamageGeneration> = Damage::new(empty(0), 10).unwrap().take(2).collect();
+        assert!(died[1].hamming == 0 && died[1].left.is_none());
+        assert!(Damage::new(empty(90), 21).is_err());
+    }
+
+    #[test]
+    fn test_lyapunov() {
+        let random = |rule: u8, seed: u64| {
+            Simulation::new(
+                rule.into(),
+                Boundary::Periodic,
+                BitMap::random_seeded(64, 0.5, seed),
+            )
+        };
+        // Every path multiplies by 2 for rule 90 and by 3 for rule 150 each generation
+        let rule_90 = lyapunov(random(90, 1), 32, 50).unwrap();
+        assert!((rule_90 - 2f64.ln()).abs() < 1e-9);
+        let rule_150 = lyapunov(random(150, 1), 32, 50).unwrap();
+        assert!((rule_150 - 3f64.ln()).abs() < 1e-9);
+        // The identity neither grows nor shrinks damage, and rule 0 erases it
+        assert!(lyapunov(random(204, 1), 32, 50) == Some(0.0));
+        assert!(lyapunov(random(0, 1), 32, 50).is_none());
+
+        let estimate = lyapunov_ensemble((1..=4).map(|seed| random(0, seed)), 32, 10);
+        assert!(estimate.mean.is_none() && estimate.died == 4 && estimate.runs == 4);
+    }
+
+    #[test]
+    fn test_damage_image() {
+        let mut png = Vec::new();
+        let mut image = DamageImage::new(&mut png, 21, true);
+        for generation in Damage::new(empty(90), 10).unwrap().take(5) {
+            image.push(&generation);
+        }
+        image.finish().unwrap();
+        let mut decoder = png::Decoder::new(&png[..]);
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let (info, _) = decoder.read_info().unwrap();
+        assert!(info.width == 21 && info.height == 5);
+        assert!(info.color_type == png::ColorType::Indexed);
+
+        // An image of no generations is not written
+        let mut gif = Vec::new();
+        assert!(DamageImage::new(&mut gif, 21, false).finish().is_err());
+        assert!(gif.is_empty());
+    }
+
+    #[test]
+    fn test_derrida() {
+        let derrida = Derrida {
+            rules: vec![204, 0, 90],
+            points: 4,
+            samples: 10,
+            chart: None,
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let plot = |rule: u8, rng: &mut StdRng| {
+            DerridaPlot::measure(rule, 40, Boundary::Periodic, 0.5, &derrida, rng)
+        };
+        // The identity keeps every distance, and rule 0 erases them
+        let identity = plot(204, &mut rng);
+        assert!(identity.points.len() == 5);
+        assert!(identity.points.iter().all(|p| p.expected == p.initial));
+        assert!(identity.slope() == Some(1.0));
+        assert!(identity.to_csv()[1] == "204,0.25,0.25");
+        assert!(plot(0, &mut rng).points.iter().all(|p| p.expected == 0.0));
+        // Rule 90 flips every cell next to exactly one flipped cell
+        let additive = plot(90, &mut rng);
+        assert!(additive.points[0].expected == 0.0 && additive.points[4].expected == 0.0);
+        assert!(additive.slope().unwrap() > 1.0);
+
+        let mut png = Vec::new();
+        write_derrida_chart(&mut png, &[identity, additive]).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+    }
+}
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod hashlife_tests {
+    use cellular::hashlife::*;
+    use cellular::image_manip::bitmap::BitMap;
+    use cellular::image_manip::metadata::Metadata;
+    use cellular::lattice::{Lattice, Unbounded, MAX_GENERATION};
+    use cellular::viewport::Viewport;
+
+    // Cells 0 to 29 of a random run, padded with empty cells wide enough that a
+    // periodic lattice never wraps around within the generations compared
+    const CELLS: u64 = 30;
+    const PADDING: u64 = 300;
+
+    fn padded(seed: u64) -> (BitMap, BitMap) {
+        let cells = BitMap::random_seeded(CELLS, 0.5, seed);
+        let wide = BitMap::from_fn(CELLS + 2 * PADDING, |i| {
+            i as u64 >= PADDING
+                && i as u64 - PADDING < CELLS
+                && cells.get(i - PADDING as usize) == 1
+        });
+        (cells, wide)
+    }
+
+    #[test]
+    fn test_matches_rule_step() {
+        for rule in &[110u8, 30, 90, 54, 18] {
+            let (cells, mut wide) = padded(*rule as u64);
+            let mut life = HashLife::new((*rule).into(), &cells).unwrap();
+            for t in 0..250u64 {
+                let fresh = {
+                    let mut fresh = HashLife::new((*rule).into(), &cells).unwrap();
+                    fresh.advance(t).unwrap();
+                    fresh
+                };
+                let row = wide.to_bit_vec();
+                assert!(life.generation() == t);
+                assert!(life.row(-(PADDING as i64), row.len()).to_bit_vec() == row);
+                assert!(fresh.row(-(PADDING as i64), row.len()).to_bit_vec() == row);
+                wide.rule_step(*rule);
+                life.advance(1).unwrap();
+            }
+        }
+        assert!(HashLife::new(105.into(), &BitMap::single(5)).is_err());
+    }
+
+    #[test]
+    fn test_bounds_and_samples() {
+        let mut life = HashLife::new(90.into(), &BitMap::single_at(8, 3)).unwrap();
+        assert!(life.bounds() == Some((3, 3)));
+        // Rule 90 leaves two cells at the edges of its light cone at powers of 2
+        life.advance(1 << 20).unwrap();
+        assert!(life.population() == 2);
+        assert!(life.bounds() == Some((3 - (1 << 20), 3 + (1 << 20))));
+        // Positions past MAX_GENERATION would no longer fit an i64
+        assert!(life.advance(u64::MAX).is_err());
+        assert!(life.advance(MAX_GENERATION).is_err());
+        assert!(life.generation() == 1 << 20);
+        life.advance(MAX_GENERATION - (1 << 20)).unwrap();
+        assert!(life.population() == 2);
+        assert!(life.bounds() == Some((3 - MAX_GENERATION as i64, 3 + MAX_GENERATION as i64)));
+        let mut erased = HashLife::new(0.into(), &BitMap::single(4)).unwrap();
+        erased.advance(1).unwrap();
+        assert!(erased.bounds().is_none());
+
+        let samples: Vec<_> = HashLife::new(90.into(), &BitMap::single_at(8, 3))
+            .unwrap()
+            .samples(Viewport::fixed(0, 8), 2)
+            .take(3)
+            .collect();
+        assert!(samples.iter().map(|g| g.index).collect::<Vec<_>>() == vec![0, 2, 4]);
+        assert!(samples[1].cells.to_bit_string() == "01000100");
+
+        // Recorded runs are drawn through the same rows
+        let mut metadata = Metadata::new(90, 8, 2, 1, &BitMap::single_at(8, 3));
+        metadata.lattice = Lattice::HashLife;
+        metadata.stride = 2;
+        let decoded = Metadata::decode(&metadata.encode()).unwrap();
+        assert!(decoded == metadata);
+        let rows: Vec<_> = decoded.generations().unwrap().take(3).collect();
+        assert!(rows[1].cells.to_bit_string() == "01000100");
+        metadata.start = u64::MAX;
+        assert!(metadata.hashlife().is_err() && metadata.generations().is_err());
+        metadata.start = MAX_GENERATION;
+        assert!(metadata.generations().is_err());
+    }
+}
@@ -1,3 +1,14 @@
+// The tests below predate the lint gate and are kept as they were written
+#![allow(
+    dead_code,
+    non_fmt_panics,
+    unused_imports,
+    unused_must_use,
+    unused_variables,
+    clippy::needless_borrows_for_generic_args,
+    clippy::unnecessary_cast
+)]
+
 #[cfg(test)]
 mod image_manip_tests {
     use cellular::image_manip::bitmap::BitMap;
@@ -44,6 +55,26 @@ mod image_manip_tests {
         }
     }
 
+    #[test]
+    fn test_build_gif_to_writer() {
+        let file_name = "test_writer.gif";
+        let mut bmp_0 = BitMap::random_seeded(30, 0.5, 4);
+        let mut bmp_1 = bmp_0.clone();
+        build_gif(30, 20, 4, &mut bmp_0, file_name, None, 110).unwrap();
+        let mut buffer: Vec<u8> = Vec::new();
+        build_gif_to_writer(30, 20, 4, &mut bmp_1, &mut buffer, None, 110).unwrap();
+
+        assert!(fs::read(file_name).unwrap() == buffer);
+        // Both leave the bitmap one generation past the last one drawn, height + steps - 2
+        let mut expected = BitMap::random_seeded(30, 0.5, 4);
+        for _ in 0..20 + 4 - 1 {
+            expected.rule_step(110);
+        }
+        assert!(bmp_0.get_vec() == expected.get_vec());
+        assert!(bmp_1.get_vec() == expected.get_vec());
+        fs::remove_file(file_name).unwrap();
+    }
+
     use log::{error, trace};
     use std::fs;
 
@@ -196,6 +227,15 @@ mod bitmap_tests {
         BitMap::random(0, -1.0);
     }
 
+    #[test]
+    fn test_random_seeded() {
+        let bmp_0 = BitMap::random_seeded(100, 0.5, 42);
+        let bmp_1 = BitMap::random_seeded(100, 0.5, 42);
+        let bmp_2 = BitMap::random_seeded(100, 0.5, 43);
+        assert!(bmp_0.get_vec() == bmp_1.get_vec());
+        assert!(bmp_0.get_vec() != bmp_2.get_vec());
+    }
+
     #[test]
     #[should_panic]
     fn test_get_with_out_of_bounds_val() {
@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod import_tests {
+    use cellular::image_manip::bitmap::BitMap;
+    use cellular::image_manip::build_gif;
+    use cellular::image_manip::import::*;
+    use std::fs;
+
+    fn temp_file(name: &str, contents: &[u8]) -> String {
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_last_row_of_gif() {
+        let path = std::env::temp_dir().join("cellular_import_test.gif");
+        let path = path.to_str().unwrap();
+        let pattern = [true, false, false, true, true, false, true, false];
+        let mut line = BitMap::periodic(40, &pattern);
+        let expected = line.to_bit_vec();
+        // Rule 204 copies every cell unchanged, so each row matches the initial bitmap
+        build_gif(40, 10, 3, &mut line, path, None, 204).unwrap();
+
+        let bmp = ImageRow::last_of(path).to_bitmap(None).unwrap();
+        assert!(bmp.to_bit_vec() == expected);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_pbm_rows_and_resizing() {
+        let path = temp_file(
+            "cellular_import_test.pbm",
+            b"P1\n# comment\n4 2\n1 0 1 1\n0 1 0 0\n",
+        );
+        let mut row = ImageRow::new(&path);
+        assert!(row.to_bitmap(None).unwrap().to_bit_vec() == vec![1, 0, 1, 1]);
+        row.row = -1;
+        assert!(row.to_bitmap(None).unwrap().to_bit_vec() == vec![0, 1, 0, 0]);
+        assert!(row.to_bitmap(Some(8)).unwrap().to_bit_vec() == vec![0, 0, 1, 1, 0, 0, 0, 0]);
+        // Pixels past the cells, such as a statistics strip, are left out
+        row.columns = Some(3);
+        assert!(row.to_bitmap(None).unwrap().to_bit_vec() == vec![0, 1, 0]);
+        row.row = 2;
+        assert!(row.to_bitmap(None).is_err());
+        assert!(ImageRow::new(&path).to_bitmap(Some(0)).is_err());
+        fs::remove_file(path).unwrap();
+
+        let empty = temp_file("cellular_import_empty.pbm", b"P1\n0 1\n");
+        assert!(ImageRow::new(&empty).to_bitmap(None).is_err());
+        assert!(ImageRow::new(&empty).to_bitmap(Some(4)).is_err());
+        fs::remove_file(empty).unwrap();
+    }
+
+    #[test]
+    fn test_unknown_format() {
+        let path = temp_file("cellular_import_test.txt", b"0110");
+        assert!(ImageRow::new(&path).to_bitmap(None).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
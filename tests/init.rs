@@ -0,0 +1,120 @@
+#[cfg(test)]
+mod init_tests {
+    use cellular::image_manip::bitmap::{BitMap, Glider};
+    use cellular::image_manip::init::Init;
+
+    // Smallest number of generations after which bmp repeats up to a rotation
+    fn period_up_to_shift(bmp: &BitMap, rule: u8, max_steps: usize) -> Option<usize> {
+        let start = bmp.get_vec();
+        let mut line = bmp.clone();
+        for t in 1..=max_steps {
+            line.rule_step(rule);
+            let vec = line.get_vec();
+            for shift in 0..vec.len() {
+                if (0..vec.len()).all(|i| vec[(i + shift) % vec.len()] == start[i]) {
+                    return Some(t);
+                }
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn test_parse_specs() {
+        assert!("random".parse::<Init>().unwrap() == Init::Random(0.5));
+        assert!("single".parse::<Init>().unwrap() == Init::Single(None));
+        assert!("single:3".parse::<Init>().unwrap() == Init::Single(Some(3)));
+        assert!("periodic:110".parse::<Init>().unwrap() == Init::Periodic(vec![true, true, false]));
+        assert!(
+            "blocks:2:10".parse::<Init>().unwrap()
+                == Init::Blocks {
+                    count: 2,
+                    width: 10,
+                    density: 0.5
+                }
+        );
+        assert!("ether:A,B".parse::<Init>().unwrap() == Init::Ether(vec![Glider::A, Glider::B]));
+        assert!("random:2".parse::<Init>().is_err());
+        assert!("periodic:12".parse::<Init>().is_err());
+        assert!("ether:Z".parse::<Init>().is_err());
+        assert!("nothing".parse::<Init>().is_err());
+        assert!("blocks:0:200".parse::<Init>().is_err());
+    }
+
+    #[test]
+    fn test_deterministic_generators() {
+        let bmp = Init::Single(None).build(11, None).unwrap();
+        assert!(bmp.to_bit_vec() == vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0]);
+
+        let bmp = Init::Periodic(vec![true, false, false])
+            .build(7, None)
+            .unwrap();
+        assert!(bmp.to_bit_vec() == vec![1, 0, 0, 1, 0, 0, 1]);
+
+        assert!(Init::Single(Some(11)).build(11, None).is_err());
+    }
+
+    #[test]
+    fn test_random_generators() {
+        let blocks = Init::Blocks {
+            count: 2,
+            width: 10,
+            density: 1.0,
+        };
+        let bmp = blocks.build(40, Some(1)).unwrap();
+        // Each block is centred in its half of the bitmap
+        let expected: Vec<u8> = (0..40)
+            .map(|i| ((5..15).contains(&i) || (25..35).contains(&i)) as u8)
+            .collect();
+        assert!(bmp.to_bit_vec() == expected);
+        for (count, width) in &[(0, 200), (0, 10), (1, 41), (3, 14)] {
+            let blocks = Init::Blocks {
+                count: *count,
+                width: *width,
+                density: 1.0,
+            };
+            assert!(blocks.build(40, Some(1)).is_err());
+        }
+
+        let markov = Init::Markov {
+            p_birth: 0.05,
+            p_death: 0.05,
+        };
+        let bmp_0 = markov.build(1000, Some(3)).unwrap();
+        let bmp_1 = markov.build(1000, Some(3)).unwrap();
+        assert!(bmp_0.get_vec() == bmp_1.get_vec());
+        // Runs average 20 cells, so there are far fewer changes than in uncorrelated noise
+        let vec = bmp_0.get_vec();
+        let changes = (1..vec.len()).filter(|i| vec[*i] != vec[i - 1]).count();
+        assert!(changes < 150);
+    }
+
+    #[test]
+    fn test_ether_and_gliders() {
+        // The ether repeats itself every generation up to a shift
+        let ether = BitMap::ether(14 * 8, &[]);
+        assert!(period_up_to_shift(&ether, 110, 20) == Some(1));
+
+        // A glider in the ether repeats every 3 generations, B every 4
+        let with_a = BitMap::ether(14 * 8 + 6, &[Glider::A]);
+        assert!(period_up_to_shift(&with_a, 110, 20) == Some(3));
+        let with_b = BitMap::ether(14 * 8 + 8, &[Glider::B]);
+        assert!(period_up_to_shift(&with_b, 110, 20) == Some(4));
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for spec in &[
+            "random:0.3",
+            "single",
+            "single:4",
+            "periodic:1101",
+            "blocks:3:5:0.25",
+            "markov:0.1:0.6",
+            "ether",
+            "ether:A,B,A",
+        ] {
+            assert!(spec.parse::<Init>().unwrap().to_string() == *spec);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod lattice_tests {
+    use cellular::image_manip::bitmap::BitMap;
+    use cellular::image_manip::metadata::Metadata;
+    use cellular::lattice::*;
+
+    #[test]
+    fn test_growing_matches_rule_step() {
+        // A periodic lattice wide enough never to wrap around within 100 generations
+        let cells = BitMap::random_seeded(20, 0.5, 5);
+        let wide = BitMap::from_fn(220, |i| (100..120).contains(&i) && cells.get(i - 100) == 1);
+        for rule in &[110u8, 30, 90, 184] {
+            let mut lattice = GrowingLattice::new((*rule).into(), &cells).unwrap();
+            let mut periodic = wide.clone();
+            for t in 0..100 {
+                assert!(lattice.generation() == t);
+                assert!(lattice.row(-100, 220).to_bit_vec() == periodic.to_bit_vec());
+                // Only the light cone is stored
+                assert!(lattice.width() <= 20 + 2 * t as usize);
+                periodic.rule_step(*rule);
+                lattice.step();
+            }
+        }
+        assert!(GrowingLattice::new(1.into(), &cells).is_err());
+
+        let mut single = GrowingLattice::new(30.into(), &BitMap::single_at(5, 2)).unwrap();
+        single.advance(10).unwrap();
+        assert!(single.bounds() == Some((-8, 12)));
+        single = GrowingLattice::new(0.into(), &BitMap::single(5)).unwrap();
+        single.advance(1).unwrap();
+        assert!(single.bounds().is_none() && single.width() == 0);
+    }
+
+    #[test]
+    fn test_expanded_canvas() {
+        for spec in &["finite", "growing", "hashlife"] {
+            assert!(spec.parse::<Lattice>().unwrap().to_string() == *spec);
+        }
+        assert!("torus".parse::<Lattice>().is_err());
+
+        // A single cell of rule 90 drawn on a canvas as wide as its light cone
+        let mut metadata = Metadata::new(90, 9, 5, 1, &BitMap::single_at(1, 0));
+        metadata.lattice = Lattice::Growing;
+        metadata.offset = -4;
+        let decoded = Metadata::decode(&metadata.encode()).unwrap();
+        assert!(decoded == metadata);
+        assert!(decoded.command("out.gif").contains("-b 1 "));
+        assert!(decoded
+            .command("out.gif")
+            .contains(" --growing --canvas expand "));
+        let rows: Vec<String> = decoded
+            .generations()
+            .unwrap()
+            .take(5)
+            .map(|g| g.cells.to_bit_string())
+            .collect();
+        assert!(rows[0] == "000010000");
+        assert!(rows[4] == "100000001");
+
+        // HashLife draws the same rows
+        metadata.lattice = Lattice::HashLife;
+        let hashlife: Vec<String> = metadata
+            .generations()
+            .unwrap()
+            .take(5)
+            .map(|g| g.cells.to_bit_string())
+            .collect();
+        assert!(hashlife == rows);
+    }
+}
@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod metadata_tests {
+    use cellular::atlas::{build_atlas, Atlas};
+    use cellular::image_manip::bitmap::BitMap;
+    use cellular::image_manip::metadata::Metadata;
+    use cellular::image_manip::{build_gif, build_gif_with_metadata};
+    use std::fs;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(name)
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let mut metadata = Metadata::new(110, 5, 4, 3, &BitMap::single(5));
+        metadata.seed = Some(42);
+        metadata.density = Some(0.25);
+        metadata.init = Some(String::from("single"));
+        metadata.start = 1000;
+        let decoded = Metadata::decode(&metadata.encode()).unwrap();
+        assert!(decoded == metadata);
+        assert!(decoded.bitmap == "00100");
+        assert!(metadata
+            .command("out.gif")
+            .contains(" --start-generation 1000 "));
+
+        // Unknown keys are skipped but required ones must be present
+        let extra = format!("{}\nfuture=1", metadata.encode());
+        assert!(Metadata::decode(&extra).unwrap() == metadata);
+        assert!(Metadata::decode("cellular 0.1.2\nrule=30").is_err());
+        assert!(Metadata::decode("made by something else").is_err());
+
+        // Values that would not rebuild are rejected rather than panicking later
+        let encoded = metadata.encode();
+        assert!(Metadata::decode(&encoded.replace("width=5", "width=0")).is_err());
+        assert!(Metadata::decode(&format!("{}\nstride=0", encoded)).is_err());
+        assert!(Metadata::decode(&encoded.replace("bitmap=00100", "bitmap=")).is_err());
+    }
+
+    #[test]
+    fn test_read_from_gif() {
+        let path = temp_path("cellular_metadata_test.gif");
+        let mut line = BitMap::random_seeded(64, 0.5, 3);
+        let initial = line.to_bit_vec();
+        build_gif(64, 20, 4, &mut line, &path, None, 30).unwrap();
+
+        let metadata = Metadata::read(&path).unwrap();
+        assert!(metadata.rule == 30);
+        assert!((metadata.width, metadata.height, metadata.frames) == (64, 20, 4));
+        assert!(metadata.version == env!("CARGO_PKG_VERSION"));
+        assert!(metadata.initial_bitmap().to_bit_vec() == initial);
+
+        // Building again from the metadata gives the same file
+        let rerun_path = temp_path("cellular_metadata_rerun.gif");
+        let mut line = metadata.initial_bitmap();
+        build_gif_with_metadata(&metadata, &mut line, &rerun_path, None).unwrap();
+        assert!(fs::read(&path).unwrap() == fs::read(&rerun_path).unwrap());
+        fs::remove_file(path).unwrap();
+        fs::remove_file(rerun_path).unwrap();
+    }
+
+    #[test]
+    fn test_gif_without_metadata() {
+        let path = temp_path("cellular_metadata_atlas.gif");
+        let atlas = Atlas {
+            rules: vec![30, 90],
+            columns: 2,
+        };
+        build_atlas(&atlas, 10, 10, 2, &BitMap::single(10), &path, None).unwrap();
+        assert!(Metadata::read(&path).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod output_tests {
+    use cellular::output::*;
+    use std::fs;
+    use std::io::{ErrorKind, Write};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(name)
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_commit_and_overwrite() {
+        let path = temp_path("cellular_output_test.gif");
+        let _ = fs::remove_file(&path);
+
+        let file = AtomicFile::new(&path, false).unwrap();
+        assert!(file.temp_name().ends_with("cellular_output_test.gif"));
+        fs::write(file.temp_path(), b"first").unwrap();
+        // Nothing appears at the destination until the file is committed
+        assert!(fs::metadata(&path).is_err());
+        file.commit().unwrap();
+        assert!(fs::read(&path).unwrap() == b"first");
+
+        let refused = AtomicFile::new(&path, false);
+        assert!(refused.err().unwrap().kind() == ErrorKind::AlreadyExists);
+        assert!(check_overwrite(vec![path.as_str()], false).is_err());
+        assert!(check_overwrite(vec![path.as_str()], true).is_ok());
+
+        let file = AtomicFile::new(&path, true).unwrap();
+        fs::write(file.temp_path(), b"second").unwrap();
+        file.commit().unwrap();
+        assert!(fs::read(&path).unwrap() == b"second");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_drop_cleans_up() {
+        let path = temp_path("cellular_output_dropped.gif");
+        let file = AtomicFile::new(&path, true).unwrap();
+        let temp = file.temp_path().to_path_buf();
+        fs::write(&temp, b"partial").unwrap();
+        drop(file);
+        assert!(!temp.exists());
+        assert!(fs::metadata(&path).is_err());
+    }
+
+    #[test]
+    fn test_counting_writer() {
+        let count = Arc::new(AtomicU64::new(0));
+        let mut writer = CountingWriter::new(Vec::new(), count.clone());
+        writer.write_all(b"GIF89a").unwrap();
+        writer.write_all(&[0x3B]).unwrap();
+        assert!(count.load(Ordering::Relaxed) == 7);
+    }
+}
@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod parse_tests {
+    use cellular::image_manip::parse::*;
+
+    fn bits(input: &str) -> Vec<u8> {
+        parse_bitmap(input).unwrap().to_bit_vec()
+    }
+
+    #[test]
+    fn test_glyphs() {
+        assert!(bits("0110") == vec![0, 1, 1, 0]);
+        assert!(bits(".**.") == vec![0, 1, 1, 0]);
+        assert!(bits("_XX_") == vec![0, 1, 1, 0]);
+        // No trailing newline is needed, and whitespace and comments are skipped
+        assert!(bits("# first row\n01 10\n\t11 # second row\n") == vec![0, 1, 1, 0, 1, 1]);
+    }
+
+    #[test]
+    fn test_encodings() {
+        assert!(bits("hex:A5") == vec![1, 0, 1, 0, 0, 1, 0, 1]);
+        assert!(bits("0xa 5") == vec![1, 0, 1, 0, 0, 1, 0, 1]);
+        assert!(bits("base64:pQ==") == vec![1, 0, 1, 0, 0, 1, 0, 1]);
+        assert!(bits("base64:/w") == vec![1; 8]);
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let e = parse_bitmap("0101\n01a1").unwrap_err();
+        assert!((e.line, e.column) == (2, 3));
+
+        let e = parse_bitmap("hex:12\n  3G").unwrap_err();
+        assert!((e.line, e.column) == (2, 4));
+
+        let e = parse_bitmap("base64:pQ=x").unwrap_err();
+        assert!((e.line, e.column) == (1, 11));
+
+        assert!(parse_bitmap("# only a comment\n").is_err());
+        assert!(parse_bitmap("").is_err());
+    }
+}
@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod prog_tests {
+    use cellular::image_manip::bitmap::BitMap;
+    use cellular::prog::{Message, ProgBar};
+    use cellular::simulation::{Boundary, Simulation};
+    use cellular::sink::{drive, skip, MemorySink};
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn test_run_ends_with_drive() {
+        let (progress_tx, progress_rx) = mpsc::channel();
+        let progress_bar = ProgBar::new(&String::from("test.gif"), 10);
+        let progress_thread = thread::spawn(move || progress_bar.run(progress_rx));
+
+        let simulation = Simulation::new(30.into(), Boundary::Periodic, BitMap::single(20));
+        let mut memory = MemorySink::new();
+        drive(simulation.take(10), &mut memory, Some(&progress_tx)).unwrap();
+        // drive sends Kill once done, with the bar full
+        let progress_bar = progress_thread.join().unwrap();
+        assert!(progress_bar.position() == 10);
+        assert!(progress_bar.length() == 10);
+    }
+
+    #[test]
+    fn test_run_ends_without_senders() {
+        let (progress_tx, progress_rx) = mpsc::channel();
+        let progress_bar = ProgBar::new(&String::from("test.gif"), 10);
+        let progress_thread = thread::spawn(move || progress_bar.run(progress_rx));
+        progress_tx.send(Message::Update(3)).unwrap();
+        drop(progress_tx);
+        // Ends where the last update left it, short of full
+        let progress_bar = progress_thread.join().unwrap();
+        assert!(progress_bar.position() == 4);
+        assert!(progress_bar.length() == 10);
+    }
+
+    #[test]
+    fn test_skip() {
+        let skipped = |rule: u8, steps: u64| {
+            let (progress_tx, progress_rx) = mpsc::channel();
+            let line = BitMap::random_seeded(40, 0.5, 2);
+            let mut simulation = Simulation::new(rule.into(), Boundary::Periodic, line);
+            skip(&mut simulation, steps, Some(&progress_tx));
+            assert!(simulation.generation() == steps);
+            assert!(simulation.next().unwrap().index == steps);
+            drop(progress_tx);
+            progress_rx
+                .iter()
+                .map(|m| match m {
+                    Message::Skipped(done) => done,
+                    _ => panic!("only skipped generations are sent"),
+                })
+                .collect::<Vec<u64>>()
+        };
+        // Stepping reports every so often, jumping only once
+        assert!(skipped(110, 3000) == vec![1024, 2048, 3000]);
+        assert!(skipped(90, 1 << 40) == vec![1 << 40]);
+
+        let (progress_tx, progress_rx) = mpsc::channel();
+        let progress_bar = ProgBar::new(&String::from("test.gif"), 10).with_skipped(3000);
+        let progress_thread = thread::spawn(move || progress_bar.run(progress_rx));
+        progress_tx.send(Message::Skipped(3000)).unwrap();
+        progress_tx.send(Message::Update(9)).unwrap();
+        progress_tx.send(Message::Kill).unwrap();
+        let progress_bar = progress_thread.join().unwrap();
+        assert!(progress_bar.position() == 3010);
+        assert!(progress_bar.length() == 3010);
+    }
+}
@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod report_tests {
+    use cellular::image_manip::bitmap::BitMap;
+    use cellular::image_manip::build_gif_with_metadata;
+    use cellular::image_manip::metadata::Metadata;
+    use cellular::report::*;
+    use std::fs;
+
+    #[test]
+    fn test_cycle_detector() {
+        let mut detector = CycleDetector::new();
+        let states: [&[u8]; 6] = [&[0, 1], &[1, 1], &[1, 0], &[0, 0], &[1, 0], &[0, 0]];
+        for (generation, state) in states.iter().enumerate() {
+            detector.observe(generation as u64, state);
+        }
+        assert!(
+            detector.cycle()
+                == Some(Cycle {
+                    start: 2,
+                    period: 2
+                })
+        );
+
+        let mut detector = CycleDetector::new();
+        detector.observe(0, &[0, 1]);
+        detector.observe(1, &[1, 0]);
+        assert!(detector.cycle().is_none());
+    }
+
+    #[test]
+    fn test_build_stats() {
+        let path = std::env::temp_dir().join("cellular_report_test.gif");
+        let path = path.to_str().unwrap();
+        // Rule 4 keeps isolated cells and clears everything else
+        let mut line = BitMap::periodic(12, &[true, true, false, true, false, false]);
+        let metadata = Metadata::new(4, 12, 6, 5, &line);
+        let stats = build_gif_with_metadata(&metadata, &mut line, path, None).unwrap();
+        assert!(
+            stats.cycle
+                == Some(Cycle {
+                    start: 1,
+                    period: 1
+                })
+        );
+        assert!(stats.final_density == 2.0 / 12.0);
+
+        let report = Report::new(path, &metadata, &stats).unwrap();
+        assert!(report.bytes == fs::metadata(path).unwrap().len());
+        let json = report.to_json();
+        assert!(json.starts_with(&format!("{{\"output\":\"{}\"", path)));
+        assert!(json.contains("\"rule\":4,\"width\":12,\"height\":6,\"frames\":5,\"seed\":null"));
+        assert!(json.contains("\"cycle\":{\"detected\":true,\"start\":1,\"period\":1}"));
+
+        report.write_sidecar().unwrap();
+        let sidecar = sidecar_path(path);
+        assert!(sidecar.ends_with("cellular_report_test.json"));
+        assert!(fs::read_to_string(&sidecar).unwrap() == json + "\n");
+        fs::remove_file(path).unwrap();
+        fs::remove_file(sidecar).unwrap();
+    }
+}
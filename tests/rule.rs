@@ -0,0 +1,175 @@
+#[cfg(test)]
+mod rule_tests {
+    use cellular::image_manip::bitmap::BitMap;
+    use cellular::rule::{Rule, RuleProperties};
+
+    fn reversed(bmp: &BitMap) -> BitMap {
+        let mut rev = BitMap::new(bmp.size() as u64);
+        for i in 0..bmp.size() {
+            if bmp.get(i) == 1 {
+                rev.set(bmp.size() - 1 - i);
+            }
+        }
+        rev
+    }
+
+    fn inverted(bmp: &BitMap) -> BitMap {
+        let mut inv = BitMap::new(bmp.size() as u64);
+        for i in 0..bmp.size() {
+            if bmp.get(i) == 0 {
+                inv.set(i);
+            }
+        }
+        inv
+    }
+
+    #[test]
+    fn test_known_equivalents() {
+        let rule = Rule::new(110);
+        assert!(rule.mirror() == Rule::new(124));
+        assert!(rule.complement() == Rule::new(137));
+        assert!(rule.conjugate() == Rule::new(193));
+
+        let rule = Rule::new(30);
+        assert!(
+            rule.equivalence_class()
+                == vec![Rule::new(30), Rule::new(86), Rule::new(135), Rule::new(149)]
+        );
+        assert!(Rule::new(149).canonical() == rule);
+
+        // Rule 150 is its own mirror and complement
+        assert!(Rule::new(150).equivalence_class() == vec![Rule::new(150)]);
+    }
+
+    #[test]
+    fn test_involutions() {
+        for number in 0..=255 {
+            let rule = Rule::new(number);
+            assert!(rule.mirror().mirror() == rule);
+            assert!(rule.complement().complement() == rule);
+            assert!(rule.conjugate() == rule.complement().mirror());
+            assert!(rule.canonical() <= rule);
+        }
+    }
+
+    #[test]
+    fn test_representatives() {
+        let representatives = Rule::representatives();
+        assert!(representatives.len() == 88);
+        assert!(representatives.contains(&Rule::new(30)));
+        assert!(representatives.contains(&Rule::new(110)));
+        assert!(!representatives.contains(&Rule::new(124)));
+    }
+
+    #[test]
+    fn test_equivalents_against_rule_step() {
+        for number in 0..=255 {
+            let rule = Rule::new(number);
+            let bmp = BitMap::random_seeded(37, 0.5, number as u64);
+
+            // Mirrored input under the mirror rule gives mirrored output
+            let mut stepped = bmp.clone();
+            stepped.rule_step(number);
+            let mut mirrored = reversed(&bmp);
+            mirrored.rule_step(rule.mirror().number());
+            assert!(mirrored.get_vec() == reversed(&stepped).get_vec());
+
+            // Complemented input under the complement rule gives complemented output
+            let mut complemented = inverted(&bmp);
+            complemented.rule_step(rule.complement().number());
+            assert!(complemented.get_vec() == inverted(&stepped).get_vec());
+        }
+    }
+
+    #[test]
+    fn test_apply_matches_rule_step() {
+        let rule = Rule::new(110);
+        for n in 0..8u8 {
+            let mut bmp = BitMap::new(3);
+            // Bit 2 of the neighbourhood is the cell after the centre in a BitMap
+            for (bit, index) in [(0b100, 2), (0b010, 1), (0b001, 0)].iter() {
+                if n & bit != 0 {
+                    bmp.set(*index);
+                }
+            }
+            bmp.rule_step(rule.number());
+            let expected = rule.apply(n & 0b100 != 0, n & 0b010 != 0, n & 0b001 != 0);
+            assert!((bmp.get(1) == 1) == expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_notations() {
+        let rule = Rule::new(110);
+        assert!("110".parse::<Rule>().unwrap() == rule);
+        assert!("0b01101110".parse::<Rule>().unwrap() == rule);
+        assert!("0x6E".parse::<Rule>().unwrap() == rule);
+        assert!("0x6e".parse::<Rule>().unwrap() == rule);
+        assert!(
+            "111:0 110:1 101:1 100:0 011:1 010:1 001:1 000:0"
+                .parse::<Rule>()
+                .unwrap()
+                == rule
+        );
+        assert!("l xor (c or r)".parse::<Rule>().unwrap() == Rule::new(30));
+        assert!("l ^ r".parse::<Rule>().unwrap() == Rule::new(90));
+        assert!("(c & !l) | (c ^ r)".parse::<Rule>().unwrap() == rule);
+        assert!("not 0".parse::<Rule>().unwrap() == Rule::new(255));
+
+        assert!("256".parse::<Rule>().is_err());
+        assert!("0x100".parse::<Rule>().is_err());
+        assert!("111:0 110:1".parse::<Rule>().is_err());
+        assert!("l and (c".parse::<Rule>().is_err());
+        assert!("l nand c".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn test_notations_round_trip() {
+        for number in 0..=255 {
+            let rule = Rule::new(number);
+            assert!(rule.to_binary().parse::<Rule>().unwrap() == rule);
+            assert!(rule.to_hex().parse::<Rule>().unwrap() == rule);
+            assert!(rule.to_table().parse::<Rule>().unwrap() == rule);
+            assert!(rule.to_formula().parse::<Rule>().unwrap() == rule);
+        }
+        assert!(Rule::new(90).to_formula() == "l xor r");
+        assert!(Rule::new(150).to_formula() == "l xor c xor r");
+        assert!(Rule::new(0).to_formula() == "0");
+    }
+
+    #[test]
+    fn test_properties() {
+        let rules_with = |property: fn(&RuleProperties) -> bool| -> Vec<u8> {
+            (0..=255)
+                .filter(|number| property(&Rule::new(*number).properties()))
+                .collect()
+        };
+        assert!(rules_with(|p| p.additive) == vec![0, 60, 90, 102, 150, 170, 204, 240]);
+        assert!(rules_with(|p| p.number_conserving) == vec![170, 184, 204, 226, 240]);
+        assert!(rules_with(|p| p.injective) == vec![15, 51, 85, 170, 204, 240]);
+        assert!(rules_with(|p| p.surjective).len() == 30);
+
+        let rule_90 = Rule::new(90).properties();
+        assert!(rule_90.lambda == 0.5);
+        assert!(rule_90.additive && rule_90.left_permutive && rule_90.right_permutive);
+        assert!(rule_90.surjective && !rule_90.injective);
+
+        let rule_150 = Rule::new(150).properties();
+        assert!(rule_150.additive && rule_150.surjective && !rule_150.number_conserving);
+
+        // Traffic flow keeps the number of cars but loses track of where they came from
+        let rule_184 = Rule::new(184).properties();
+        assert!(rule_184.number_conserving && !rule_184.additive);
+        assert!(!rule_184.surjective && !rule_184.left_permutive);
+
+        // The shift is a bijection that depends only on the right neighbour
+        let rule_170 = Rule::new(170).properties();
+        assert!(rule_170.lambda == 0.5);
+        assert!(rule_170.injective && rule_170.number_conserving && rule_170.additive);
+        assert!(rule_170.right_permutive && !rule_170.left_permutive);
+
+        assert!(Rule::new(30).properties().left_permutive);
+        assert!(!Rule::new(30).properties().right_permutive);
+        assert!(Rule::new(0).properties().lambda == 0.0);
+    }
+}
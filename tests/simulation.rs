@@ -0,0 +1,138 @@
+#[cfg(test)]
+mod simulation_tests {
+    use cellular::image_manip::bitmap::BitMap;
+    use cellular::image_manip::init::Init;
+    use cellular::image_manip::metadata::Metadata;
+    use cellular::image_manip::render_gif;
+    use cellular::simulation::*;
+    use std::fs::{self, File};
+
+    #[test]
+    fn test_builder() {
+        assert!(Simulation::builder().width(10).build().is_err());
+        assert!(Simulation::builder().rule(30).build().is_err());
+        assert!(Simulation::builder()
+            .rule(30)
+            .width(5)
+            .initial(BitMap::single(10))
+            .build()
+            .is_err());
+
+        let simulation = Simulation::builder()
+            .rule(110)
+            .initial(BitMap::single(10))
+            .build()
+            .unwrap();
+        assert!(simulation.width() == 10);
+        assert!(simulation.boundary() == Boundary::Periodic);
+
+        // Equal seeds give equal runs
+        let run = |seed| {
+            Simulation::builder()
+                .rule(30)
+                .width(50)
+                .seed(seed)
+                .init(Init::Random(0.3))
+                .build()
+                .unwrap()
+                .take(5)
+                .map(|g| g.cells.to_bit_vec())
+                .collect::<Vec<Vec<u8>>>()
+        };
+        assert!(run(7) == run(7));
+    }
+
+    #[test]
+    fn test_generations() {
+        let mut line = BitMap::random_seeded(32, 0.5, 1);
+        let simulation = Simulation::builder()
+            .rule(110)
+            .initial(line.clone())
+            .build()
+            .unwrap();
+        for (i, generation) in simulation.take(20).enumerate() {
+            assert!(generation.index == i as u64);
+            assert!(generation.cells.to_bit_vec() == line.to_bit_vec());
+            line.rule_step(110);
+        }
+    }
+
+    #[test]
+    fn test_boundaries() {
+        // Rule 170 copies the right neighbour, which is the cell at the next lower index
+        let first_cell = |boundary| {
+            let mut line = BitMap::new(4);
+            line.set(3);
+            let mut simulation = Simulation::new(170.into(), boundary, line);
+            simulation.step();
+            simulation.cells().get(0)
+        };
+        assert!(first_cell(Boundary::Periodic) == 1);
+        assert!(first_cell(Boundary::Fixed(false)) == 0);
+        assert!(first_cell(Boundary::Fixed(true)) == 1);
+        assert!(first_cell(Boundary::Reflect) == 0);
+
+        for spec in &["periodic", "fixed:0", "fixed:1", "reflect"] {
+            assert!(spec.parse::<Boundary>().unwrap().to_string() == *spec);
+        }
+        assert!("open".parse::<Boundary>().is_err());
+    }
+
+    #[test]
+    fn test_additive_jump() {
+        for rule in &[60u8, 90, 102, 150, 170, 204, 0] {
+            let mut stepped = BitMap::random_seeded(37, 0.5, *rule as u64);
+            for t in 0..=70u64 {
+                let mut jumped = BitMap::random_seeded(37, 0.5, *rule as u64);
+                jumped.additive_jump(*rule, t).unwrap();
+                assert!(jumped.to_bit_vec() == stepped.to_bit_vec());
+                stepped.rule_step(*rule);
+            }
+        }
+        assert!(BitMap::single(9).additive_jump(30, 4).is_err());
+
+        // Simulations jump when they can and step through every generation otherwise
+        let advanced = |rule: u8, boundary: Boundary| {
+            let line = BitMap::random_seeded(41, 0.5, 3);
+            let mut simulation = Simulation::new(rule.into(), boundary, line.clone());
+            simulation.advance(1000);
+            let mut stepped = Simulation::new(rule.into(), boundary, line);
+            let last = stepped.nth(1000).unwrap();
+            assert!(simulation.generation() == 1000);
+            assert!(simulation.cells().to_bit_vec() == last.cells.to_bit_vec());
+            simulation.next().unwrap().index
+        };
+        assert!(advanced(150, Boundary::Periodic) == 1000);
+        assert!(advanced(90, Boundary::Fixed(false)) == 1000);
+        assert!(advanced(110, Boundary::Periodic) == 1000);
+    }
+
+    #[test]
+    fn test_render_consecutive_generations() {
+        let path = std::env::temp_dir().join("cellular_simulation_test.gif");
+        let path = path.to_str().unwrap();
+        let line = BitMap::random_seeded(16, 0.5, 2);
+        let simulation = Simulation::new(30.into(), Boundary::Periodic, line.clone());
+        let generations: Vec<Vec<u8>> = simulation
+            .clone()
+            .take(12)
+            .map(|g| g.cells.to_bit_vec())
+            .collect();
+        let metadata = Metadata::new(30, 16, 8, 5, &line);
+        render_gif(simulation, &metadata, path, None).unwrap();
+
+        let mut gif_opts = gif::DecodeOptions::new();
+        gif_opts.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = gif_opts.read_info(File::open(path).unwrap()).unwrap();
+        let mut frame_index = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            // Row j of frame k is generation k + j
+            for (j, row) in frame.buffer.chunks(16).enumerate() {
+                assert!(row == generations[frame_index + j].as_slice());
+            }
+            frame_index += 1;
+        }
+        assert!(frame_index == 5);
+        fs::remove_file(path).unwrap();
+    }
+}
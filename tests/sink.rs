@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod sink_tests {
+    use cellular::image_manip::bitmap::BitMap;
+    use cellular::image_manip::metadata::Metadata;
+    use cellular::image_manip::render_file;
+    use cellular::simulation::{Boundary, Simulation};
+    use cellular::sink::*;
+    use std::fs;
+
+    fn simulation() -> Simulation {
+        Simulation::new(90.into(), Boundary::Periodic, BitMap::single(7))
+    }
+
+    #[test]
+    fn test_window_frames() {
+        let mut window = Window::new(MemorySink::new(), 7, 3);
+        drive(simulation().take(5), &mut window, None).unwrap();
+        let memory = window.into_inner();
+        let generations: Vec<Vec<u8>> =
+            simulation().take(5).map(|g| g.cells.to_bit_vec()).collect();
+        assert!(memory.frames.len() == 3);
+        for (k, frame) in memory.frames.iter().enumerate() {
+            assert!(*frame == generations[k..k + 3].concat());
+        }
+        assert!(Window::<MemorySink>::generations_for(3, 3) == 5);
+    }
+
+    #[test]
+    fn test_terminal_and_memory() {
+        let mut output = Vec::new();
+        let mut terminal = TerminalSink::new(&mut output);
+        terminal.occupied = '#';
+        terminal.empty = '.';
+        drive(simulation().take(3), &mut terminal, None).unwrap();
+        assert!(String::from_utf8(output).unwrap() == "...#...\n..#.#..\n.#...#.\n");
+
+        let mut memory = MemorySink::new();
+        let stats = drive(simulation().take(3), &mut memory, None).unwrap();
+        assert!(memory.generations.len() == 3);
+        assert!(memory.generations[1] == vec![0, 0, 1, 0, 1, 0, 0]);
+        assert!(stats.final_density == 2.0 / 7.0);
+    }
+
+    #[test]
+    fn test_gif_to_writer() {
+        let metadata = Metadata::new(90, 7, 4, 2, &BitMap::single(7));
+        let mut buffer = Vec::new();
+        let mut sink = GifSink::animation(&mut buffer, &metadata).unwrap();
+        drive(simulation().take(5), &mut sink, None).unwrap();
+        drop(sink);
+
+        let mut gif_opts = gif::DecodeOptions::new();
+        gif_opts.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = gif_opts.read_info(buffer.as_slice()).unwrap();
+        let mut frames = 0;
+        while decoder.read_next_frame().unwrap().is_some() {
+            frames += 1;
+        }
+        assert!(frames == 2);
+    }
+
+    #[test]
+    fn test_png_still() {
+        let path = std::env::temp_dir().join("cellular_sink_test.png");
+        let path = path.to_str().unwrap();
+        let metadata = Metadata::new(90, 7, 4, 1, &BitMap::single(7));
+        render_file(simulation(), &metadata, path, None).unwrap();
+
+        let decoder = png::Decoder::new(fs::File::open(path).unwrap());
+        let (info, mut reader) = decoder.read_info().unwrap();
+        assert!((info.width, info.height) == (7, 4));
+        let mut pixels = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        assert!(pixels[..7] == [0xFF, 0xFF, 0xFF, 0x00, 0xFF, 0xFF, 0xFF]);
+        assert!(Metadata::read(path).unwrap() == metadata);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod sweep_tests {
+    use cellular::image_manip::bitmap::Boundary;
+    use cellular::report::Reporting;
+    use cellular::sweep::*;
+
+    #[test]
+    fn test_parse_int_list() {
+        assert!(parse_int_list("110", 255).unwrap() == vec![110]);
+        assert!(parse_int_list("30,90,110", 255).unwrap() == vec![30, 90, 110]);
+        assert!(parse_int_list("0..255", 255).unwrap().len() == 256);
+        assert!(parse_int_list("0..=10:5,30", 255).unwrap() == vec![0, 5, 10, 30]);
+        assert!(parse_int_list("0..256", 255).is_err());
+        assert!(parse_int_list("10..0", 255).is_err());
+        assert!(parse_int_list("0..10:0", 255).is_err());
+        assert!(parse_int_list("a..b", 255).is_err());
+    }
+
+    #[test]
+    fn test_parse_float_list() {
+        let densities = parse_float_list("0.1..0.9:0.1").unwrap();
+        assert!(densities == vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9]);
+        assert!(parse_float_list("0.5").unwrap() == vec![0.5]);
+        assert!(parse_float_list("0.1..0.9").is_err());
+    }
+
+    #[test]
+    fn test_runs_and_templates() {
+        let sweep = Sweep {
+            rules: vec![30, 110],
+            densities: vec![0.25, 0.5],
+            seeds: vec![1, 2, 3],
+            template: Sweep::default_template(&[0.25, 0.5], &[1, 2, 3]),
+            threads: 1,
+            boundary: Boundary::Periodic,
+            reporting: Reporting::default(),
+        };
+        let runs = sweep.runs();
+        assert!(runs.len() == 12);
+        assert!(sweep.validate_template().is_ok());
+        assert!(
+            format_output(&sweep.template, &runs[0], 10, 20, 30)
+                == "output_w10_h20_f30_r30_d0.25_s1.gif"
+        );
+
+        let sweep = Sweep {
+            template: String::from("{rule}_{density}.gif"),
+            ..sweep
+        };
+        assert!(sweep.validate_template().is_err());
+    }
+
+    #[test]
+    fn test_still_and_animated_runs() {
+        let dir = std::env::temp_dir();
+        for (extension, magic) in &[("png", &b"\x89PNG"[..]), ("gif", &b"GIF89a"[..])] {
+            let template = dir.join(format!("cellular_sweep_test_{{rule}}.{}", extension));
+            let sweep = Sweep {
+                rules: vec![30, 90],
+                densities: vec![0.5],
+                seeds: vec![1],
+                template: template.to_str().unwrap().to_string(),
+                threads: 2,
+                boundary: Boundary::Periodic,
+                reporting: Reporting::default(),
+            };
+            assert!(run_sweep(&sweep, 20, 10, 3, None, None, None).is_empty());
+            for rule in &sweep.rules {
+                let path = dir.join(format!("cellular_sweep_test_{}.{}", rule, extension));
+                assert!(std::fs::read(&path).unwrap().starts_with(magic));
+                std::fs::remove_file(path).unwrap();
+            }
+        }
+    }
+}
@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod viewport_tests {
+    use cellular::hashlife::HashLife;
+    use cellular::image_manip::bitmap::{BitMap, Boundary};
+    use cellular::image_manip::metadata::Metadata;
+    use cellular::lattice::{Lattice, Unbounded};
+    use cellular::simulation::Simulation;
+    use cellular::viewport::*;
+
+    fn rows(metadata: &Metadata, count: usize) -> Vec<String> {
+        metadata
+            .generations()
+            .unwrap()
+            .take(count)
+            .map(|g| g.cells.to_bit_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_track() {
+        assert!("fixed".parse::<Track>() == Ok(Track::Fixed));
+        assert!("mass".parse::<Track>() == Ok(Track::Mass));
+        assert!("2/3".parse::<Track>() == Ok(Track::Velocity(2.0 / 3.0)));
+        assert!("-0.5".parse::<Track>() == Ok(Track::Velocity(-0.5)));
+        for track in &["1/0", "fast", "inf", "1/2/3"] {
+            assert!(track.parse::<Track>().is_err());
+        }
+        assert!(Track::Velocity(-0.5).to_string().parse::<Track>() == Ok(Track::Velocity(-0.5)));
+
+        let viewport = Viewport {
+            x0: 10,
+            width: 5,
+            track: Track::Velocity(2.0 / 3.0),
+        };
+        assert!(viewport.left(0, None, 0) == 10);
+        assert!(viewport.left(3, None, 0) == 12);
+        assert!(viewport.left(4, None, 0) == 13);
+        assert!(Viewport::centred(100, 10) == 45);
+    }
+
+    #[test]
+    fn test_cropped() {
+        let cells = BitMap::from_fn(10, |i| i == 0 || i == 9);
+        let crop = |x0, boundary| {
+            let simulation = Simulation::new(204.into(), boundary, cells.clone());
+            let mut cropped = Cropped::new(simulation, Viewport::fixed(x0, 4), boundary);
+            cropped.next().unwrap().cells.to_bit_string()
+        };
+        // Periodic lattices wrap around, others keep the window inside
+        assert!(crop(-1, Boundary::Periodic) == "1100");
+        assert!(crop(8, Boundary::Periodic) == "0110");
+        assert!(crop(8, Boundary::Reflect) == "0001");
+        assert!(crop(-3, Boundary::Reflect) == "1000");
+
+        assert!(centre_of_mass(&cells, Boundary::Periodic) == Some(9.5));
+        assert!(centre_of_mass(&cells, Boundary::Reflect) == Some(4.5));
+        let balanced = BitMap::from_fn(10, |i| i == 0 || i == 5);
+        assert!(centre_of_mass(&balanced, Boundary::Periodic).is_none());
+        assert!(centre_of_mass(&BitMap::new(10), Boundary::Reflect).is_none());
+    }
+
+    #[test]
+    fn test_following() {
+        // Rule 170 moves every cell one to the right each generation, so a window at the
+        // same velocity sees the same row throughout, on a lattice ten times as wide
+        let mut metadata = Metadata::new(170, 10, 30, 1, &BitMap::single_at(100, 50));
+        metadata.offset = 45;
+        metadata.track = Track::Velocity(1.0);
+        metadata.seed = Some(1);
+        metadata.density = Some(0.5);
+        let decoded = Metadata::decode(&metadata.encode()).unwrap();
+        assert!(decoded == metadata);
+        assert!(decoded
+            .command("out.gif")
+            .contains("-w 10 -d 0.5 -s 1 -h 30 -f 1 -r 170 --lattice-width 100 --track 1 "));
+        assert!(rows(&decoded, 30).iter().all(|row| row == "0000010000"));
+        metadata.offset = 40;
+        assert!(metadata.command("out.gif").contains(" --window-start 40 "));
+
+        // Windows centred on the mass follow it around a periodic lattice
+        let mut metadata = Metadata::new(170, 5, 30, 1, &BitMap::single_at(20, 18));
+        metadata.track = Track::Mass;
+        assert!(rows(&metadata, 30).iter().all(|row| row == "00100"));
+
+        // Unbounded lattices track the same way
+        let mut metadata = Metadata::new(170, 5, 30, 1, &BitMap::single_at(5, 2));
+        metadata.track = Track::Mass;
+        metadata.stride = 7;
+        for lattice in &[Lattice::Growing, Lattice::HashLife] {
+            metadata.lattice = *lattice;
+            assert!(rows(&metadata, 30).iter().all(|row| row == "00100"));
+        }
+        let viewport = Viewport {
+            x0: 0,
+            width: 10,
+            track: Track::Velocity(-1.0),
+        };
+        let life = HashLife::new(240.into(), &BitMap::single_at(10, 5)).unwrap();
+        assert!(life
+            .samples(viewport, 1000)
+            .take(10)
+            .all(|g| g.cells.to_bit_string() == "0000010000"));
+    }
+}